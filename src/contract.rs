@@ -1,10 +1,10 @@
 #[cfg(not(feature = "library"))]
 use crate::error::ContractError;
 use crate::execute;
-use crate::models::ContractResult;
+use crate::models::{ContractResult, RAFFLE_STAGE_UPCOMING};
 use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 use crate::query;
-use crate::state::{self};
+use crate::state::{self, IX_U64_STATUS, RAFFLE};
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response};
 use cw2::set_contract_version;
@@ -14,14 +14,31 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-  deps: DepsMut,
+  mut deps: DepsMut,
   env: Env,
   info: MessageInfo,
   msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
   set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-  state::initialize(deps, &env, &info, &msg)?;
-  Ok(Response::new().add_attribute("action", "instantiate"))
+  state::initialize(deps.branch(), &env, &info, &msg)?;
+
+  let resp = Response::new().add_attribute("action", "instantiate");
+
+  // list the raffle as upcoming if ticket sales open in the future. nothing
+  // runs when sales open, so the index stays upcoming until the first sale;
+  // clients compare ticket_sales_start_at against the current time instead.
+  if RAFFLE.load(deps.storage)?.is_upcoming(env.block.time) {
+    return Ok(
+      resp.add_message(
+        state::repository(deps.storage)?
+          .update()
+          .set_u64(IX_U64_STATUS, RAFFLE_STAGE_UPCOMING as u64)
+          .build_msg()?,
+      ),
+    );
+  }
+
+  Ok(resp)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -39,11 +56,27 @@ pub fn execute(
       count,
       message,
       is_visible,
-    } => execute::buy_tickets(deps, env, info, count, message, is_visible),
-    ExecuteMsg::ChooseWinner {} => execute::choose_winner(deps, env, info),
+      proof,
+      allocation,
+      referrer,
+    } => execute::buy_tickets(
+      deps, env, info, count, message, is_visible, proof, allocation, referrer,
+    ),
+    ExecuteMsg::ClaimReferralEarnings {} => execute::claim_referral_earnings(deps, env, info),
+    ExecuteMsg::ChooseWinner { secret } => execute::choose_winner(deps, env, info, secret),
+    ExecuteMsg::CommitSecret { commitment } => execute::commit_secret(deps, env, info, commitment),
     ExecuteMsg::Cancel {} => execute::cancel(deps, env, info),
     ExecuteMsg::ClaimRefund {} => execute::claim_refund(deps, env, info),
     ExecuteMsg::Update { marketing } => execute::update(deps, env, info, &marketing),
+    ExecuteMsg::ReceiveNft(msg) => execute::receive_nft(deps, env, info, msg),
+    ExecuteMsg::Receive(msg) => execute::receive(deps, env, info, msg),
+    ExecuteMsg::Fund {} => execute::fund(deps, env, info),
+    ExecuteMsg::Sweep { token, recipient } => execute::sweep(deps, env, info, token, recipient),
+    ExecuteMsg::UpdateAllowlist { add, remove } => {
+      execute::update_allowlist(deps, env, info, add, remove)
+    },
+    ExecuteMsg::GrantTickets { grants } => execute::grant_tickets(deps, env, info, grants),
+    ExecuteMsg::NoisReceive { callback } => execute::nois_receive(deps, env, info, callback),
   }
 }
 
@@ -57,15 +90,24 @@ pub fn query(
     QueryMsg::Select { fields, wallet } => to_binary(&query::select(deps, fields, wallet)?),
     QueryMsg::RefundStatus { claimant } => to_binary(&query::refund_status(deps, &claimant)?),
     QueryMsg::Random {} => to_binary(&query::test_random(deps, &env)?),
+    QueryMsg::FundingStatus {} => to_binary(&query::funding_status(deps)?),
+    QueryMsg::PayoutPreview {} => to_binary(&query::payout_preview(deps, &env)?),
+    QueryMsg::Eligibility { wallet } => to_binary(&query::eligibility(deps, &wallet)?),
+    QueryMsg::QuotePrice { count, wallet } => to_binary(&query::quote_price(deps, count, wallet)?),
+    QueryMsg::Referrals { referrer } => to_binary(&query::referrals(deps, &referrer)?),
+    QueryMsg::DrawProof {} => to_binary(&query::draw_proof(deps)?),
+    QueryMsg::Tickets { wallet } => to_binary(&query::tickets(deps, &wallet)?),
   }?;
   Ok(result)
 }
 
 #[entry_point]
 pub fn migrate(
-  _deps: DepsMut,
-  _env: Env,
+  deps: DepsMut,
+  env: Env,
   _msg: MigrateMsg,
 ) -> Result<Response, ContractError> {
+  set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+  state::migrate(deps, &env)?;
   Ok(Response::default())
 }
@@ -27,12 +27,81 @@ pub enum ContractError {
   #[error("AlreadyClaimed")]
   AlreadyClaimed {},
 
+  #[error("NotEligible: {reason}")]
+  NotEligible { reason: String },
+
+  #[error("InvalidMerkleProof")]
+  InvalidMerkleProof {},
+
+  #[error("SalesNotStarted")]
+  SalesNotStarted {},
+
   #[error("SalesPeriodOver")]
   SalesPeriodOver {},
 
   #[error("SoldOut")]
   SoldOut {},
 
+  #[error("TicketLimitExceeded: {remaining} remaining")]
+  TicketLimitExceeded { remaining: u32 },
+
+  #[error("NftNotEscrowed")]
+  NftNotEscrowed {},
+
+  #[error("InvalidNft")]
+  InvalidNft {},
+
+  #[error("NotFunded")]
+  NotFunded {},
+
+  #[error("ExcessFunds")]
+  ExcessFunds {},
+
+  #[error("InvalidToken")]
+  InvalidToken {},
+
+  #[error("InvalidPaymentAmount")]
+  InvalidPaymentAmount {},
+
+  #[error("InvalidAddress: {address}")]
+  InvalidAddress { address: String },
+
+  #[error("InvalidRoyaltyTotal: {total}")]
+  InvalidRoyaltyTotal { total: u32 },
+
+  #[error("DuplicateRoyaltyRecipient: {address}")]
+  DuplicateRoyaltyRecipient { address: String },
+
+  #[error("InvalidFeeTotal: {total_bps}")]
+  InvalidFeeTotal { total_bps: u32 },
+
+  #[error("DuplicateFeeRecipient: {address}")]
+  DuplicateFeeRecipient { address: String },
+
+  #[error("NotEnded")]
+  NotEnded {},
+
+  #[error("AlreadyCommitted")]
+  AlreadyCommitted {},
+
+  #[error("CommitmentRequired")]
+  CommitmentRequired {},
+
+  #[error("InvalidSecret")]
+  InvalidSecret {},
+
+  #[error("RevealDeadlinePassed")]
+  RevealDeadlinePassed {},
+
+  #[error("InvalidRandomness")]
+  InvalidRandomness {},
+
+  #[error("SelfReferral")]
+  SelfReferral {},
+
+  #[error("RefundsPending")]
+  RefundsPending {},
+
   #[error("ValidationError")]
   ValidationError { reason: Option<String> },
 }
@@ -1,23 +1,29 @@
 use crate::{
   error::ContractError,
-  models::{ContractResult, TicketOrder, WalletMetadata, RAFFLE_STAGE_HAS_BUYERS},
+  gate::check_eligibility,
+  merkle::{build_leaf, verify_proof},
+  models::{
+    ContractResult, Raffle, RaffleStatus, ReferralEarnings, TicketOrder, WalletMetadata,
+    RAFFLE_STAGE_HAS_BUYERS,
+  },
   state::{
-    repository, HOUSE_ADDR, IX_U64_STATUS, IX_U64_TICKETS_SOLD, IX_U64_WALLET_COUNT, RAFFLE,
-    TICKET_ORDERS, WALLET_METADATA,
+    fee_config, index_tickets, repository, IX_U64_STATUS, IX_U64_TICKETS_SOLD, IX_U64_WALLET_COUNT,
+    RAFFLE, REFERRAL_EARNINGS, TICKET_ORDERS, WALLET_METADATA,
   },
 };
-use cosmwasm_std::{attr, Addr, Binary, DepsMut, Empty, Env, MessageInfo, Response, Uint128};
+use cosmwasm_std::{
+  attr, Addr, Api, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response, Storage, Uint128,
+};
+use cw20::Cw20ReceiveMsg;
 use cw_lib::{
-  models::Token,
+  models::{Token, TokenAmount},
   random::{Pcg64, RngComponent},
-  utils::funds::{
-    build_cw20_transfer_from_msg, build_send_msg, has_funds, require_cw20_token_balance,
-  },
 };
 use house_staking::client::House;
 
-const GLTO_CW20_ADDR: &str = "juno1j0a9ymgngasfn3l5me8qpd53l5zlm9wurfdk7r65s5mg6tkxal3qpgf5se";
-
+/// Buy tickets with the native token sent in info.funds. Raffles priced in a
+/// cw20 token sell tickets through the cw20 Receive hook instead.
+#[allow(clippy::too_many_arguments)]
 pub fn buy_tickets(
   deps: DepsMut,
   env: Env,
@@ -25,16 +31,240 @@ pub fn buy_tickets(
   count: u32,
   message: Option<String>,
   is_visible: bool,
+  proof: Option<Vec<String>>,
+  allocation: Option<u32>,
+  referrer: Option<Addr>,
 ) -> Result<Response, ContractError> {
-  let buyer = &info.sender;
-  let mut raffle = RAFFLE.load(deps.storage)?;
+  let (raffle, order) = prepare_order(
+    deps.as_ref(),
+    &env,
+    &info.sender,
+    None,
+    count,
+    is_visible,
+    proof,
+    allocation,
+    referrer,
+  )?;
+
+  // verify buyer sent exactly the ticket price in the expected denom
+  match &raffle.price.token {
+    Token::Native { denom } => {
+      if info.funds.is_empty() {
+        return Err(ContractError::MissingFunds {});
+      }
+      if info.funds.len() > 1 || info.funds[0].denom != *denom {
+        return Err(ContractError::InvalidToken {});
+      }
+      if Some(info.funds[0].amount) != order.amount_paid {
+        return Err(ContractError::InvalidPaymentAmount {});
+      }
+    },
+    Token::Cw20 { .. } => {
+      return Err(ContractError::InvalidToken {});
+    },
+  }
+
+  process_order(deps, &env, raffle, order, message)
+}
+
+/// Buy tickets with cw20 tokens sent to the contract through the cw20 Receive
+/// hook, in a single `Send` from the buyer.
+#[allow(clippy::too_many_arguments)]
+pub fn buy_tickets_cw20(
+  deps: DepsMut,
+  env: Env,
+  cw20_addr: &Addr,
+  receive_msg: &Cw20ReceiveMsg,
+  count: u32,
+  message: Option<String>,
+  is_visible: bool,
+  proof: Option<Vec<String>>,
+  allocation: Option<u32>,
+  referrer: Option<Addr>,
+) -> ContractResult<Response> {
+  let buyer = deps.api.addr_validate(&receive_msg.sender)?;
+  let payment = TokenAmount {
+    token: Token::Cw20 {
+      address: cw20_addr.clone(),
+    },
+    amount: receive_msg.amount,
+  };
+  let (raffle, order) = prepare_order(
+    deps.as_ref(),
+    &env,
+    &buyer,
+    Some(&payment),
+    count,
+    is_visible,
+    proof,
+    allocation,
+    referrer,
+  )?;
+
+  // verify that the tokens received are the ones the tickets are priced in
+  match &raffle.price.token {
+    Token::Cw20 { address } if address == cw20_addr => {
+      if Some(receive_msg.amount) != order.amount_paid {
+        return Err(ContractError::InvalidPaymentAmount {});
+      }
+    },
+    _ => {
+      return Err(ContractError::InvalidToken {});
+    },
+  }
+
+  process_order(deps, &env, raffle, order, message)
+}
+
+/// Validate a ticket order against the raffle's limits, sale gate, presale
+/// proof and referrer, returning the raffle and the unpaid order. Callers only
+/// need to verify payment of the order's amount before processing it. Tokens
+/// the buyer has already transferred as payment are given as `payment`.
+#[allow(clippy::too_many_arguments)]
+fn prepare_order(
+  deps: Deps,
+  env: &Env,
+  buyer: &Addr,
+  payment: Option<&TokenAmount>,
+  count: u32,
+  is_visible: bool,
+  proof: Option<Vec<String>>,
+  allocation: Option<u32>,
+  referrer: Option<Addr>,
+) -> ContractResult<(Raffle, TicketOrder)> {
+  let raffle = RAFFLE.load(deps.storage)?;
+
+  validate_order(deps.storage, &raffle, env, buyer, count)?;
+
+  // abort if the buyer doesn't pass the raffle's sale gate
+  if let Some(reason) = check_eligibility(deps, &raffle, buyer, payment)? {
+    return Err(ContractError::NotEligible { reason });
+  }
+
+  validate_presale_proof(deps.storage, &raffle, buyer, count, &proof, allocation)?;
+
+  let referrer = validate_referrer(deps.api, buyer, referrer)?;
+
+  let order = TicketOrder {
+    address: buyer.clone(),
+    is_visible,
+    count,
+    amount_paid: Some(raffle.get_order_price(count)),
+    house_revenue: None,
+    is_free: false,
+    referrer,
+    first_ticket: None,
+    last_ticket: None,
+    time: Some(env.block.time),
+  };
+
+  Ok((raffle, order))
+}
+
+/// If the raffle has a presale merkle root, ensure the buyer's proof is valid
+/// and, if the buyer has a ticket allocation, that the order doesn't exceed it.
+fn validate_presale_proof(
+  storage: &dyn Storage,
+  raffle: &Raffle,
+  buyer: &Addr,
+  count: u32,
+  proof: &Option<Vec<String>>,
+  allocation: Option<u32>,
+) -> ContractResult<()> {
+  if let Some(root) = &raffle.merkle_root {
+    let proof = proof.as_ref().ok_or(ContractError::InvalidMerkleProof {})?;
+    if !verify_proof(root, build_leaf(buyer.as_str(), allocation), proof)? {
+      return Err(ContractError::InvalidMerkleProof {});
+    }
+    if let Some(allocation) = allocation {
+      let allocation_used = WALLET_METADATA
+        .may_load(storage, buyer.clone())?
+        .map(|meta| meta.allocation_used)
+        .unwrap_or(0);
+      let remaining = allocation.saturating_sub(allocation_used);
+      if count > remaining {
+        return Err(ContractError::TicketLimitExceeded { remaining });
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Validate the referrer's address, rejecting buyers who refer themselves.
+fn validate_referrer(
+  api: &dyn Api,
+  buyer: &Addr,
+  referrer: Option<Addr>,
+) -> ContractResult<Option<Addr>> {
+  if let Some(referrer) = referrer {
+    let referrer = api.addr_validate(referrer.as_str())?;
+    if referrer == *buyer {
+      return Err(ContractError::SelfReferral {});
+    }
+    return Ok(Some(referrer));
+  }
+  Ok(None)
+}
+
+/// Ensure tickets can be sold in the raffle's current state.
+fn validate_order(
+  storage: &dyn Storage,
+  raffle: &Raffle,
+  env: &Env,
+  buyer: &Addr,
+  count: u32,
+) -> ContractResult<()> {
+  // abort if the raffle has already ended
+  if raffle.status != RaffleStatus::Active {
+    return Err(ContractError::NotActive {});
+  }
+
+  if count == 0 {
+    return Err(ContractError::ValidationError {
+      reason: Some("orders must be for at least 1 ticket".into()),
+    });
+  }
 
   // abort if there aren't enough tickets left
   if let Some(ticket_supply) = raffle.ticket_supply {
     let tickets_remaining = ticket_supply - raffle.tickets_sold;
-    if count == 0 || tickets_remaining < count {
+    if tickets_remaining < count {
       return Err(ContractError::SoldOut {});
     }
+  } else if raffle.tickets_sold.checked_add(count).is_none() {
+    return Err(ContractError::SoldOut {});
+  }
+
+  // abort if the order exceeds the per-order or per-wallet ticket limits
+  let wallet_ticket_count = WALLET_METADATA
+    .may_load(storage, buyer.clone())?
+    .map(|meta| meta.ticket_count)
+    .unwrap_or(0);
+  if let Some(remaining) = raffle.get_ticket_allowance(wallet_ticket_count) {
+    if count > remaining {
+      return Err(ContractError::TicketLimitExceeded { remaining });
+    }
+  }
+
+  // abort if the owner hasn't committed to their commit-reveal secret yet
+  if raffle.is_awaiting_commitment() {
+    return Err(ContractError::CommitmentRequired {});
+  }
+
+  // abort if any NFT being raffled hasn't been sent to the contract yet
+  if !raffle.is_escrow_complete() {
+    return Err(ContractError::NftNotEscrowed {});
+  }
+
+  // abort if the contract doesn't hold every token prize yet
+  if !raffle.is_funded() {
+    return Err(ContractError::NotFunded {});
+  }
+
+  // abort if ticket sales haven't opened yet
+  if raffle.is_upcoming(env.block.time) {
+    return Err(ContractError::SalesNotStarted {});
   }
 
   // abort if ticket sales period expired
@@ -44,58 +274,70 @@ pub fn buy_tickets(
     }
   }
 
+  Ok(())
+}
+
+/// Process a paid ticket order, forwarding house revenue, accruing referral
+/// earnings and updating the RNG seed, wallet metadata and repository indices.
+fn process_order(
+  deps: DepsMut,
+  env: &Env,
+  mut raffle: Raffle,
+  mut order: TicketOrder,
+  message: Option<String>,
+) -> ContractResult<Response> {
   // init return response, accumulating additional submessages below
   let mut resp: Response<Empty> = Response::new().add_attributes(vec![
     attr("action", "buy_tickets"),
-    attr("count", count.to_string()),
+    attr("count", order.count.to_string()),
   ]);
 
-  let balance_required = Uint128::from(count) * raffle.price.amount;
+  // if a house revenue rule exists for the ticket price token, forward the
+  // house's share of the payment to the house contract. the house's share is
+  // recorded so that refunds only return what the contract actually holds.
+  let fees = fee_config(deps.storage)?;
+  if let Some(rule) = fees.get_house_revenue(&raffle.price.token) {
+    let house_revenue = order
+      .amount_paid
+      .unwrap_or_default()
+      .multiply_ratio(rule.bps, 10_000u128);
+    let house = House::new(&rule.house_address);
+    resp = resp.add_messages(house.receive(raffle.price.token.clone(), house_revenue, None)?);
+    order.house_revenue = Some(house_revenue);
+  }
 
-  // verify buyer can make payment
-  match &raffle.price.token {
-    Token::Native { denom } => {
-      // ensure info.funds is as expected:
-      if has_funds(&info.funds, balance_required, denom) {
-        resp = resp.add_message(build_send_msg(
-          &env.contract.address,
-          denom,
-          balance_required,
-        )?);
-      } else {
-        return Err(ContractError::MissingFunds {});
-      }
-    },
-    Token::Cw20 { address: cw20_addr } => {
-      require_cw20_token_balance(deps.querier, buyer, balance_required, cw20_addr, false)?;
-      let is_glto = *cw20_addr == Addr::unchecked(GLTO_CW20_ADDR);
-      let house_revenue = if is_glto {
-        balance_required.multiply_ratio(5u128, 100u128)
-      } else {
-        Uint128::zero()
-      };
-      // NOTE: to allow the contract to transfer CW20 tokens to itself, this
-      // function must come after a msg to the CW20 token's increase_allowance
-      // function in the same transaction.
-      resp = resp.add_message(build_cw20_transfer_from_msg(
-        buyer,
-        &env.contract.address,
-        cw20_addr,
-        balance_required - house_revenue,
-      )?);
-      // If the token used is GLTO, send 5% of the payment amount to the house.
-      if is_glto {
-        let house = House::new(&Addr::unchecked(HOUSE_ADDR));
-        resp = resp.add_messages(house.receive(raffle.price.token.clone(), house_revenue, None)?)
-      }
-    },
+  // credit the referrer with their share of the payment. the share stays in
+  // the contract until claimed and is refunded to the buyer on cancelation.
+  if let Some(referrer) = &order.referrer {
+    let amount_paid = order.amount_paid.unwrap_or_default();
+    let earnings = amount_paid.multiply_ratio(fees.referral_bps, 10_000u128);
+    REFERRAL_EARNINGS.update(
+      deps.storage,
+      referrer.clone(),
+      |maybe_referral| -> ContractResult<ReferralEarnings> {
+        let mut referral = maybe_referral.unwrap_or(ReferralEarnings {
+          volume: Uint128::zero(),
+          order_count: 0,
+          ticket_count: 0,
+          earnings: Uint128::zero(),
+          has_claimed: false,
+        });
+        referral.volume += amount_paid;
+        referral.order_count += 1;
+        referral.ticket_count += order.count;
+        referral.earnings += earnings;
+        Ok(referral)
+      },
+    )?;
+    raffle.referral_earnings += earnings;
+    resp = resp.add_attribute("referrer", referrer.to_string());
   }
 
-  // update tickets sold and RNG seed
+  // update RNG seed
   raffle.seed = Binary::from(Pcg64::build_seed(&vec![
     RngComponent::Str(raffle.seed.clone()),
     RngComponent::Str(message.clone().unwrap_or("".to_string())),
-    RngComponent::Str(buyer.to_string()),
+    RngComponent::Str(order.address.to_string()),
     RngComponent::Int(env.block.time.nanos()),
     RngComponent::Int(env.block.height),
     RngComponent::Int(
@@ -109,15 +351,62 @@ pub fn buy_tickets(
   ]))
   .to_base64();
 
+  record_order(deps.storage, env, &mut raffle, &mut order, message)?;
+
+  RAFFLE.save(deps.storage, &raffle)?;
+
+  Ok(
+    resp.add_message(
+      repository(deps.storage)?
+        .update()
+        .set_u64(IX_U64_TICKETS_SOLD, raffle.tickets_sold.into())
+        .set_u64(IX_U64_WALLET_COUNT, raffle.wallet_count.into())
+        .set_u64(IX_U64_STATUS, RAFFLE_STAGE_HAS_BUYERS as u64)
+        .add_relationship(&order.address, "player")
+        .build_msg()?,
+    ),
+  )
+}
+
+/// Add a ticket order to the raffle, assigning its ticket numbers and updating
+/// wallet metadata and tickets sold. The raffle itself is saved by the caller.
+pub fn record_order(
+  storage: &mut dyn Storage,
+  env: &Env,
+  raffle: &mut Raffle,
+  order: &mut TicketOrder,
+  message: Option<String>,
+) -> ContractResult<()> {
+  let count = order.count;
+  let is_visible = order.is_visible;
+  let amount_paid = order.amount_paid.unwrap_or_default();
+  let house_revenue = order.house_revenue.unwrap_or_default();
+  let free_ticket_count = if order.is_free { count } else { 0 };
+
+  // tickets bought in a presale count towards the buyer's allocation
+  let allocation_used = if raffle.merkle_root.is_some() && !order.is_free {
+    count
+  } else {
+    0
+  };
+
   // update wallet-level metadata
   WALLET_METADATA.update(
-    deps.storage,
-    buyer.clone(),
+    storage,
+    order.address.clone(),
     |maybe_meta| -> ContractResult<WalletMetadata> {
       if let Some(mut meta) = maybe_meta {
+        meta.amount_paid = Some(meta.get_amount_paid(raffle.price.amount) + amount_paid);
         meta.ticket_count += count;
         meta.ticket_order_count += 1;
-        meta.display_message = if is_visible { message.clone() } else { None };
+        meta.free_ticket_count += free_ticket_count;
+        meta.allocation_used += allocation_used;
+        meta.house_revenue = Some(meta.house_revenue.unwrap_or_default() + house_revenue);
+        meta.last_ordered_at = Some(env.block.time);
+        // free grants leave the wallet's lucky message as it is
+        if !order.is_free {
+          meta.display_message = if is_visible { message.clone() } else { None };
+        }
         Ok(meta)
       } else {
         raffle.wallet_count += 1;
@@ -127,34 +416,27 @@ pub fn buy_tickets(
           has_agreed_to_terms: true,
           ticket_order_count: 1,
           ticket_count: count,
+          free_ticket_count,
+          allocation_used,
+          amount_paid: Some(amount_paid),
+          house_revenue: Some(house_revenue),
+          last_ordered_at: Some(env.block.time),
           display_message: if is_visible { message.clone() } else { None },
         })
       }
     },
   )?;
 
-  TICKET_ORDERS.push_back(
-    deps.storage,
-    &TicketOrder {
-      address: buyer.clone(),
-      is_visible,
-      count,
-    },
-  )?;
+  // ticket numbers are assigned in purchase order
+  order.first_ticket = Some(raffle.tickets_sold);
+  order.last_ticket = Some(raffle.tickets_sold + count - 1);
+  index_tickets(storage, &order.address, raffle.tickets_sold, count)?;
 
-  raffle.tickets_sold += count;
+  TICKET_ORDERS.push_back(storage, order)?;
 
-  RAFFLE.save(deps.storage, &raffle)?;
+  raffle.tickets_sold += count;
+  raffle.free_tickets += free_ticket_count;
+  raffle.refunds_outstanding += amount_paid.saturating_sub(house_revenue);
 
-  Ok(
-    resp.add_message(
-      repository(deps.storage)?
-        .update()
-        .set_u64(IX_U64_TICKETS_SOLD, raffle.tickets_sold.into())
-        .set_u64(IX_U64_WALLET_COUNT, raffle.wallet_count.into())
-        .set_u64(IX_U64_STATUS, RAFFLE_STAGE_HAS_BUYERS as u64)
-        .add_relationship(&buyer, "player")
-        .build_msg()?,
-    ),
-  )
+  Ok(())
 }
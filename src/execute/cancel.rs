@@ -1,9 +1,12 @@
 use crate::{
   error::ContractError,
-  models::{ContractResult, RaffleAsset, RaffleStatus, RAFFLE_STAGE_CANCELED},
-  state::{is_allowed, repository, IX_U64_STATUS, RAFFLE, RAFFLE_OWNER},
+  models::{
+    ContractResult, Raffle, RaffleAsset, RaffleStatus, RandomnessSource, RAFFLE_STAGE_CANCELED,
+  },
+  nft::build_cw721_transfer_msg,
+  state::{is_allowed, repository, DRAW_TIMEOUT_SECONDS, IX_U64_STATUS, RAFFLE, RAFFLE_OWNER},
 };
-use cosmwasm_std::{attr, CosmosMsg, DepsMut, Env, MessageInfo, Response, SubMsg};
+use cosmwasm_std::{attr, CosmosMsg, DepsMut, Env, MessageInfo, Response, SubMsg, Uint128};
 use cw_lib::{
   models::Token,
   utils::funds::{build_cw20_transfer_submsg, build_send_msg},
@@ -11,53 +14,100 @@ use cw_lib::{
 
 pub fn cancel(
   deps: DepsMut,
-  _env: Env,
+  env: Env,
   info: MessageInfo,
 ) -> ContractResult<Response> {
-  if !is_allowed(&deps.as_ref(), &info.sender, "cancel")? {
+  let raffle = RAFFLE.load(deps.storage)?;
+
+  // if the owner fails to reveal their commit-reveal secret in time, anyone
+  // can cancel the raffle so that ticket holders can claim refunds
+  let is_reveal_overdue = match &raffle.randomness_source {
+    RandomnessSource::CommitReveal {
+      reveal_deadline, ..
+    } => env.block.time >= *reveal_deadline,
+    _ => false,
+  };
+
+  if !is_reveal_overdue && !is_allowed(&deps.as_ref(), &info.sender, "cancel")? {
     return Err(ContractError::NotAuthorized {});
   }
 
-  let mut raffle = RAFFLE.load(deps.storage)?;
-
-  // prevent raffle from being double-ended
-  if raffle.status != RaffleStatus::Active {
+  // prevent raffle from being double-ended. a raffle stuck waiting on the
+  // randomness beacon can be canceled once the draw has timed out.
+  let is_draw_timed_out = raffle.status == RaffleStatus::Drawing
+    && raffle
+      .draw_requested_at
+      .map(|t| env.block.time >= t.plus_seconds(DRAW_TIMEOUT_SECONDS))
+      .unwrap_or(false);
+  if raffle.status != RaffleStatus::Active && !is_draw_timed_out {
     return Err(ContractError::NotAuthorized {});
   }
 
+  Ok(
+    refund_raffle(deps, &env, raffle, RaffleStatus::Canceled)?
+      .add_attributes(vec![attr("action", "cancel")]),
+  )
+}
+
+/// End the raffle with the given refundable status, returning escrowed and
+/// funded prize assets to the raffle owner. Ticket holders can then claim
+/// refunds.
+pub fn refund_raffle(
+  deps: DepsMut,
+  env: &Env,
+  mut raffle: Raffle,
+  status: RaffleStatus,
+) -> ContractResult<Response> {
   let mut cw20_transfer_msgs: Vec<SubMsg> = vec![];
-  let mut native_transfer_msgs: Vec<CosmosMsg> = vec![];
+  let mut send_msgs: Vec<CosmosMsg> = vec![];
 
-  // send contract balance back to raffle owner
-  // build msgs to transfer auto-transferable assets
+  // build msgs to transfer auto-transferable assets back to raffle owner
   let owner = RAFFLE_OWNER.load(deps.storage)?;
   for asset in raffle.assets.iter() {
-    if let RaffleAsset::Token { token, amount, .. } = &asset {
-      match token {
-        Token::Native { denom } => {
-          native_transfer_msgs.push(build_send_msg(&owner, denom, *amount)?)
-        },
-        Token::Cw20 { address: cw20_addr } => {
-          cw20_transfer_msgs.push(build_cw20_transfer_submsg(&owner, cw20_addr, *amount)?)
-        },
-      }
+    match asset {
+      RaffleAsset::Token {
+        token,
+        funded_amount,
+        ..
+      } if !funded_amount.is_zero() => {
+        match token {
+          Token::Native { denom } => send_msgs.push(build_send_msg(&owner, denom, *funded_amount)?),
+          Token::Cw20 { address: cw20_addr } => cw20_transfer_msgs.push(
+            build_cw20_transfer_submsg(&owner, cw20_addr, *funded_amount)?,
+          ),
+        }
+      },
+      RaffleAsset::Nft {
+        collection_address,
+        token_id,
+        is_escrowed: true,
+        ..
+      } => send_msgs.push(build_cw721_transfer_msg(
+        collection_address,
+        token_id,
+        &owner,
+      )?),
+      _ => {},
     }
   }
 
-  raffle.status = RaffleStatus::Canceled;
+  raffle.status = status;
+  raffle.canceled_at = Some(env.block.time);
+
+  // referral earnings are voided, as buyers are refunded what they paid
+  raffle.referral_earnings = Uint128::zero();
 
   RAFFLE.save(deps.storage, &raffle)?;
 
   Ok(
     Response::new()
-      .add_attributes(vec![attr("action", "cancel")])
       .add_message(
         repository(deps.storage)?
           .update()
           .set_u64(IX_U64_STATUS, RAFFLE_STAGE_CANCELED as u64)
           .build_msg()?,
       )
-      .add_messages(native_transfer_msgs)
+      .add_messages(send_msgs)
       .add_submessages(cw20_transfer_msgs),
   )
 }
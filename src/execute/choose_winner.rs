@@ -1,48 +1,68 @@
 use crate::{
   error::ContractError,
-  models::{ContractResult, RaffleAsset, RaffleStatus, RAFFLE_STAGE_COMPLETED},
-  selection::draw_winner,
-  state::{is_allowed, repository, IX_U64_STATUS, RAFFLE, RAFFLE_OWNER, ROYALTIES},
+  models::{
+    ContractResult, Raffle, RaffleAsset, RaffleStatus, RandomnessSource, RAFFLE_STAGE_COMPLETED,
+  },
+  msg::ProxyExecuteMsg,
+  nft::build_cw721_transfer_msg,
+  payout::load_payouts,
+  selection::draw_winners,
+  state::{
+    is_allowed, repository, BEACON_JOB_ID, DRAW_RECORD, IX_U64_STATUS, RAFFLE, RAFFLE_OWNER,
+  },
+};
+use cosmwasm_std::{
+  attr, to_binary, CosmosMsg, DepsMut, Env, HexBinary, MessageInfo, Response, SubMsg, WasmMsg,
 };
-use cosmwasm_std::{attr, Addr, CosmosMsg, DepsMut, Env, MessageInfo, Response, SubMsg, Uint128};
 use cw_lib::{
   models::Token,
-  utils::funds::{build_cw20_transfer_submsg, build_send_msg, get_token_balance},
+  utils::funds::{build_cw20_transfer_submsg, build_send_msg},
 };
+use sha2::{Digest, Sha256};
 
-// addresses for gelotto taxes:
-pub const GELOTTO_ADDR: &str = "juno1jume25ttjlcaqqjzjjqx9humvze3vcc8z87szj";
-pub const GELOTTO_NFT_1_REWARDS_ADDR: &str = "juno18fd2xax0uh9dxusg8uae5rkeu8a4sv3gk6zm7h";
-pub const GELOTTO_NFT_2_REWARDS_ADDR: &str = "juno13c97054tjktvzvgqe2xfxj28j6wmhhlz03ut32";
-pub const GELOTTO_OWNERS_ADDR: &str = "juno1dunhw3y4m6lu642lk20hfq9q3scr70l2vuyrwj";
-
-// percentages for tax allocations:
-pub const GELOTTO_PCT: u128 = 200_000;
-pub const GELOTTO_NFT_1_REWARDS_PCT: u128 = 200_000;
-pub const GELOTTO_NFT_2_REWARDS_PCT: u128 = 200_000;
-pub const GELOTTO_OWNERS_PCT: u128 = 200_000;
-pub const RAFFLE_CREATOR_PCT: u128 = 200_000;
+use super::cancel::refund_raffle;
 
 pub fn choose_winner(
   deps: DepsMut,
   env: Env,
   info: MessageInfo,
+  secret: Option<String>,
 ) -> ContractResult<Response> {
+  let mut raffle = RAFFLE.load(deps.storage)?;
+
+  // prevent raffle from being double-ended
+  if raffle.status != RaffleStatus::Active || !raffle.winners.is_empty() {
+    return Err(ContractError::NotActive {});
+  }
+
+  // if the sales period ended without reaching the ticket sales target, the
+  // raffle fails so that ticket holders can claim refunds. anyone can trigger
+  // this, so refunds don't depend on the owner. only paid tickets count
+  // towards the target, so granting tickets can't stave off failure.
+  if let Some(target) = raffle.ticket_sales_target {
+    if raffle.get_paid_ticket_count() < target {
+      if let Some(sales_end_at) = raffle.ticket_sales_end_at {
+        if env.block.time >= sales_end_at {
+          return Ok(
+            refund_raffle(deps, &env, raffle, RaffleStatus::Failed)?.add_attributes(vec![
+              attr("action", "choose_winner"),
+              attr("status", "failed"),
+            ]),
+          );
+        }
+      }
+      return Err(ContractError::BelowTicketSalesThreshold {});
+    }
+  }
+
   if !is_allowed(&deps.as_ref(), &info.sender, "choose_winner")? {
     return Err(ContractError::NotAuthorized {});
   }
 
-  let mut raffle = RAFFLE.load(deps.storage)?;
-
   if raffle.tickets_sold == 0 {
     return Err(ContractError::AlreadyClaimed {});
   }
 
-  // prevent raffle from being double-ended
-  if raffle.status != RaffleStatus::Active || raffle.winner_address.is_some() {
-    return Err(ContractError::NotActive {});
-  }
-
   // ensure the game status can transition from active if there's a timer on
   // this raffle but time hasn't expired, only continue if the raffle's tickets
   // are completely sold out. otherwise, make them wait.
@@ -52,103 +72,164 @@ pub fn choose_winner(
     }
   }
 
+  // raffles using commit-reveal randomness draw from the owner's secret,
+  // which must match the commitment made before the first ticket was issued
+  let entropy = match &raffle.randomness_source {
+    RandomnessSource::CommitReveal {
+      commitment,
+      reveal_deadline,
+    } => {
+      if env.block.time >= *reveal_deadline {
+        return Err(ContractError::RevealDeadlinePassed {});
+      }
+      let secret = secret.ok_or(ContractError::InvalidSecret {})?;
+      let commitment = commitment.as_ref().ok_or(ContractError::InvalidSecret {})?;
+      if hex::encode(Sha256::digest(secret.as_bytes())) != commitment.to_lowercase() {
+        return Err(ContractError::InvalidSecret {});
+      }
+      Some(HexBinary::from(secret.as_bytes()))
+    },
+    _ => None,
+  };
+
+  // raffles drawing from a randomness beacon request randomness from the
+  // beacon's proxy and draw winners once the proxy calls back. ticket sales
+  // stay closed in the meantime.
+  if let RandomnessSource::Beacon { proxy_address } = &raffle.randomness_source {
+    let proxy_address = proxy_address.clone();
+    raffle.status = RaffleStatus::Drawing;
+    raffle.draw_requested_at = Some(env.block.time);
+    RAFFLE.save(deps.storage, &raffle)?;
+    return Ok(
+      Response::new()
+        .add_attributes(vec![
+          attr("action", "choose_winner"),
+          attr("status", "drawing"),
+        ])
+        .add_message(WasmMsg::Execute {
+          contract_addr: proxy_address.into(),
+          msg: to_binary(&ProxyExecuteMsg::GetNextRandomness {
+            job_id: BEACON_JOB_ID.to_string(),
+          })?,
+          funds: info.funds,
+        }),
+    );
+  }
+
+  Ok(
+    settle_draw(deps, &env, raffle, entropy.as_ref())?
+      .add_attributes(vec![attr("action", "choose_winner")]),
+  )
+}
+
+/// Draw winners, transfer prizes to them and pay out proceeds, completing the
+/// raffle. `entropy` is the beacon's randomness or the owner's revealed
+/// secret, if the raffle uses either.
+pub fn settle_draw(
+  deps: DepsMut,
+  env: &Env,
+  mut raffle: Raffle,
+  entropy: Option<&HexBinary>,
+) -> ContractResult<Response> {
   let mut cw20_transfer_msgs: Vec<SubMsg> = vec![];
   let mut send_msgs: Vec<CosmosMsg> = vec![];
 
-  // randomly select the winner wallet address
-  let winning_addr = draw_winner(deps.storage, &mut raffle, &env)?;
+  let owner = RAFFLE_OWNER.load(deps.storage)?;
 
-  // build msgs to transfer auto-transferable assets from contract to winner
+  // randomly select one winner wallet address per prize rank, keeping a record
+  // of the draw so that anyone can reproduce it
+  let record = draw_winners(deps.storage, &raffle, env, entropy)?;
+  raffle.winners = record
+    .draws
+    .iter()
+    .map(|drawn| (drawn.rank, drawn.winner.clone()))
+    .collect();
+  DRAW_RECORD.save(deps.storage, &record)?;
+
+  // build msgs to transfer auto-transferable assets from contract to the
+  // winner of each asset's rank. if there weren't enough distinct wallets to
+  // fill every rank, unawarded assets go back to the raffle owner.
   for asset in raffle.assets.iter() {
-    if let RaffleAsset::Token { token, amount, .. } = &asset {
-      match token {
-        Token::Native { denom } => send_msgs.push(build_send_msg(&winning_addr, denom, *amount)?),
-        Token::Cw20 { address: cw20_addr } => cw20_transfer_msgs.push(build_cw20_transfer_submsg(
-          &winning_addr,
-          cw20_addr,
-          *amount,
-        )?),
-      }
+    let recipient = raffle.get_winner(asset.rank()).unwrap_or(&owner);
+    match asset {
+      RaffleAsset::Token { token, amount, .. } => match token {
+        Token::Native { denom } => send_msgs.push(build_send_msg(recipient, denom, *amount)?),
+        Token::Cw20 { address: cw20_addr } => {
+          cw20_transfer_msgs.push(build_cw20_transfer_submsg(recipient, cw20_addr, *amount)?)
+        },
+      },
+      RaffleAsset::Nft {
+        collection_address,
+        token_id,
+        is_escrowed: true,
+        ..
+      } => send_msgs.push(build_cw721_transfer_msg(
+        collection_address,
+        token_id,
+        recipient,
+      )?),
+      _ => {},
     }
   }
 
-  let total_pot: Uint128 = Uint128::from(raffle.tickets_sold) * raffle.price.amount;
-  let total_royalties = total_pot.multiply_ratio(900_000u128, 1_000_000u128);
-  let balance = get_token_balance(deps.querier, &env.contract.address, &raffle.price.token)?;
-  let total_taxes = balance - total_royalties;
-
-  // let total_amount = get_token_balance(deps.querier, &env.contract.address, &raffle.price.token)?;
-  let owner = RAFFLE_OWNER.load(deps.storage)?;
-  let mut total_tax_pct = 0u128;
-
-  // prepare list of (addr, tax_amount) tuples for building send msgs
-  let tax_payouts: Vec<(Addr, Uint128)> = [
-    (GELOTTO_ADDR, GELOTTO_PCT),
-    (GELOTTO_NFT_1_REWARDS_ADDR, GELOTTO_NFT_1_REWARDS_PCT),
-    (GELOTTO_NFT_2_REWARDS_ADDR, GELOTTO_NFT_2_REWARDS_PCT),
-    (GELOTTO_OWNERS_ADDR, GELOTTO_OWNERS_PCT),
-    (owner.as_str(), RAFFLE_CREATOR_PCT),
-  ]
-  .iter()
-  .map(|(s, n)| {
-    total_tax_pct += *n;
-    (Addr::unchecked(*s), Uint128::from(*n))
-  })
-  .collect();
-
-  // build transfer msgs for sending proceeds to royalty recipients and gelotto
-  match &raffle.price.token {
-    Token::Native { denom } => {
-      // send royalties
-      for result in ROYALTIES.iter(deps.storage)? {
-        if let Ok(recipient) = result {
-          let amount = total_royalties.multiply_ratio(recipient.pct, 100u128);
-          send_msgs.push(build_send_msg(&recipient.address, denom, amount)?);
-        }
-      }
-      // send gelotto taxes
-      for (addr, pct) in tax_payouts.iter() {
-        let tax_amount = total_taxes.multiply_ratio(*pct, 1_000_000u128);
-        send_msgs.push(build_send_msg(addr, denom, tax_amount)?);
-      }
-    },
-    Token::Cw20 { address: cw20_addr } => {
-      // send royalties
-      for result in ROYALTIES.iter(deps.storage)? {
-        if let Ok(recipient) = result {
-          let amount = (Uint128::from(recipient.pct) * total_royalties) / Uint128::from(100u8);
-          cw20_transfer_msgs.push(build_cw20_transfer_submsg(
-            &recipient.address,
-            cw20_addr,
-            amount,
-          )?);
-        }
-      }
-      // send gelotto taxes
-      for (addr, pct) in tax_payouts.iter() {
-        let tax_amount = total_taxes.multiply_ratio(*pct, 1_000_000u128);
-        cw20_transfer_msgs.push(build_cw20_transfer_submsg(addr, cw20_addr, tax_amount)?);
-      }
-    },
+  // build transfer msgs for sending proceeds to royalty and fee recipients
+  for (addr, amount) in load_payouts(deps.as_ref(), env, &raffle)?.iter() {
+    match &raffle.price.token {
+      Token::Native { denom } => send_msgs.push(build_send_msg(addr, denom, *amount)?),
+      Token::Cw20 { address: cw20_addr } => {
+        cw20_transfer_msgs.push(build_cw20_transfer_submsg(addr, cw20_addr, *amount)?)
+      },
+    }
   }
 
   raffle.status = RaffleStatus::Complete;
-  raffle.winner_address = Some(winning_addr.clone());
+  raffle.winner_address = raffle.get_winner(1).cloned();
 
   RAFFLE.save(deps.storage, &raffle)?;
 
-  let resp = Response::new()
-    .add_attributes(vec![attr("action", "choose_winner")])
+  let mut resp = Response::new()
     .add_messages(send_msgs)
     .add_submessages(cw20_transfer_msgs);
 
-  Ok(
-    resp.add_message(
-      repository(deps.storage)?
-        .update()
-        .set_u64(IX_U64_STATUS, RAFFLE_STAGE_COMPLETED as u64)
-        .add_relationship(&winning_addr, "winner")
-        .build_msg()?,
-    ),
-  )
+  for (rank, addr) in raffle.winners.iter() {
+    resp = resp.add_attribute(format!("winner_{}", rank), addr.to_string());
+  }
+
+  // publish the inputs and outputs of the draw
+  resp = resp.add_attributes(vec![
+    attr("draw_seed", record.seed.clone()),
+    attr("draw_block_time", record.block_time.nanos().to_string()),
+    attr("draw_block_height", record.block_height.to_string()),
+    attr("draw_tx_index", record.tx_index.to_string()),
+    attr("draw_total_tickets", record.total_tickets.to_string()),
+  ]);
+  if let Some(entropy) = &record.entropy {
+    resp = resp.add_attribute("draw_entropy", entropy.to_hex());
+  }
+  for drawn in record.draws.iter() {
+    resp = resp.add_attributes(vec![
+      attr(
+        format!("draw_{}_value", drawn.rank),
+        drawn.random_value.to_string(),
+      ),
+      attr(
+        format!("draw_{}_ticket_index", drawn.rank),
+        drawn.ticket_index.to_string(),
+      ),
+      attr(
+        format!("winning_ticket_{}", drawn.rank),
+        drawn.ticket_number.to_string(),
+      ),
+    ]);
+  }
+
+  let mut repo_update = repository(deps.storage)?
+    .update()
+    .set_u64(IX_U64_STATUS, RAFFLE_STAGE_COMPLETED as u64);
+
+  for (_, addr) in raffle.winners.iter() {
+    repo_update = repo_update.add_relationship(addr, "winner");
+  }
+
+  Ok(resp.add_message(repo_update.build_msg()?))
 }
@@ -0,0 +1,61 @@
+use crate::{
+  error::ContractError,
+  models::{ContractResult, RaffleStatus},
+  state::{RAFFLE, REFERRAL_EARNINGS},
+};
+use cosmwasm_std::{attr, DepsMut, Env, MessageInfo, Response};
+use cw_lib::{
+  models::Token,
+  utils::funds::{build_cw20_transfer_submsg, build_send_msg},
+};
+
+/// Pay a referrer their share of referred ticket sales. Earnings can only be
+/// claimed once a winner has been chosen. If the raffle is canceled instead,
+/// earnings are voided and the funds go back to buyers as refunds.
+pub fn claim_referral_earnings(
+  deps: DepsMut,
+  _env: Env,
+  info: MessageInfo,
+) -> ContractResult<Response> {
+  let mut raffle = RAFFLE.load(deps.storage)?;
+
+  match raffle.status {
+    RaffleStatus::Complete => {},
+    RaffleStatus::Active | RaffleStatus::Drawing => return Err(ContractError::NotEnded {}),
+    _ => return Err(ContractError::NotAuthorized {}),
+  }
+
+  let mut referral = REFERRAL_EARNINGS
+    .may_load(deps.storage, info.sender.clone())?
+    .ok_or(ContractError::NotAuthorized {})?;
+
+  if referral.has_claimed {
+    return Err(ContractError::AlreadyClaimed {});
+  }
+
+  referral.has_claimed = true;
+  REFERRAL_EARNINGS.save(deps.storage, info.sender.clone(), &referral)?;
+
+  raffle.referral_earnings -= referral.earnings;
+  RAFFLE.save(deps.storage, &raffle)?;
+
+  let resp = Response::new().add_attributes(vec![
+    attr("action", "claim_referral_earnings"),
+    attr("amount", referral.earnings.to_string()),
+  ]);
+
+  if referral.earnings.is_zero() {
+    return Ok(resp);
+  }
+
+  Ok(match &raffle.price.token {
+    Token::Native { denom } => {
+      resp.add_message(build_send_msg(&info.sender, denom, referral.earnings)?)
+    },
+    Token::Cw20 { address: cw20_addr } => resp.add_submessage(build_cw20_transfer_submsg(
+      &info.sender,
+      cw20_addr,
+      referral.earnings,
+    )?),
+  })
+}
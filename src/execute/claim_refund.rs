@@ -1,9 +1,9 @@
 use crate::{
   error::ContractError,
-  models::{ContractResult, RaffleStatus},
+  models::ContractResult,
   state::{RAFFLE, REFUND_STATUSES, WALLET_METADATA},
 };
-use cosmwasm_std::{attr, CosmosMsg, DepsMut, Env, MessageInfo, Response, SubMsg, Uint128};
+use cosmwasm_std::{attr, CosmosMsg, DepsMut, Env, MessageInfo, Response, SubMsg};
 use cw_lib::{
   models::Token,
   utils::funds::{build_cw20_transfer_submsg, build_send_msg},
@@ -14,10 +14,10 @@ pub fn claim_refund(
   _env: Env,
   info: MessageInfo,
 ) -> ContractResult<Response> {
-  let raffle = RAFFLE.load(deps.storage)?;
+  let mut raffle = RAFFLE.load(deps.storage)?;
 
-  // only canceled raffles can issue refunds
-  if raffle.status != RaffleStatus::Canceled {
+  // only canceled or failed raffles can issue refunds
+  if !raffle.is_refundable() {
     return Err(ContractError::NotAuthorized {});
   }
 
@@ -45,17 +45,22 @@ pub fn claim_refund(
     wallet_meta.has_claimed_refund = true;
     WALLET_METADATA.save(deps.storage, info.sender.clone(), &wallet_meta)?;
 
-    // get total cost for all buyer's tickets
-    let refund_amount = Uint128::from(wallet_meta.ticket_count) * raffle.price.amount;
-    match &raffle.price.token {
-      Token::Native { denom } => {
-        native_transfer_msgs.push(build_send_msg(&info.sender, denom, refund_amount)?)
-      },
-      Token::Cw20 { address: cw20_addr } => cw20_transfer_msgs.push(build_cw20_transfer_submsg(
-        &info.sender,
-        cw20_addr,
-        refund_amount,
-      )?),
+    // get total amount the contract received for all buyer's tickets
+    let refund_amount = wallet_meta.get_refund_amount(raffle.price.amount);
+    raffle.refunds_outstanding = raffle.refunds_outstanding.saturating_sub(refund_amount);
+
+    // wallets holding only free tickets have nothing to refund
+    if !refund_amount.is_zero() {
+      match &raffle.price.token {
+        Token::Native { denom } => {
+          native_transfer_msgs.push(build_send_msg(&info.sender, denom, refund_amount)?)
+        },
+        Token::Cw20 { address: cw20_addr } => cw20_transfer_msgs.push(build_cw20_transfer_submsg(
+          &info.sender,
+          cw20_addr,
+          refund_amount,
+        )?),
+      }
     }
   }
 
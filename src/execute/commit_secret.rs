@@ -0,0 +1,64 @@
+use crate::{
+  error::ContractError,
+  models::{ContractResult, RaffleStatus, RandomnessSource},
+  state::{is_allowed, validate_commitment, RAFFLE},
+};
+use cosmwasm_std::{attr, DepsMut, Env, MessageInfo, Response};
+
+/// Commit to the hex-encoded sha256 hash of the secret used to draw winners in
+/// a commit-reveal raffle. The commitment must be made before the first ticket
+/// is issued, so that the owner can't choose a secret knowing what buyers have
+/// contributed to the raffle's seed, and can't be changed once made.
+pub fn commit_secret(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  commitment: String,
+) -> ContractResult<Response> {
+  if !is_allowed(&deps.as_ref(), &info.sender, "commit_secret")? {
+    return Err(ContractError::NotAuthorized {});
+  }
+
+  let mut raffle = RAFFLE.load(deps.storage)?;
+
+  if raffle.status != RaffleStatus::Active {
+    return Err(ContractError::NotActive {});
+  }
+
+  // abort if tickets have already been issued or ticket sales have closed
+  if raffle.tickets_sold > 0 {
+    return Err(ContractError::ValidationError {
+      reason: Some("commitment must be made before tickets are issued".into()),
+    });
+  }
+  if let Some(deadline) = raffle.ticket_sales_end_at {
+    if env.block.time >= deadline {
+      return Err(ContractError::SalesPeriodOver {});
+    }
+  }
+
+  validate_commitment(&commitment)?;
+
+  match &mut raffle.randomness_source {
+    RandomnessSource::CommitReveal {
+      commitment: Some(_),
+      ..
+    } => return Err(ContractError::AlreadyCommitted {}),
+    RandomnessSource::CommitReveal {
+      commitment: current,
+      ..
+    } => *current = Some(commitment.to_lowercase()),
+    _ => {
+      return Err(ContractError::ValidationError {
+        reason: Some("raffle doesn't use commit-reveal randomness".into()),
+      })
+    },
+  }
+
+  RAFFLE.save(deps.storage, &raffle)?;
+
+  Ok(Response::new().add_attributes(vec![
+    attr("action", "commit_secret"),
+    attr("commitment", commitment),
+  ]))
+}
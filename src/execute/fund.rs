@@ -0,0 +1,72 @@
+use crate::{
+  error::ContractError,
+  models::{ContractResult, RaffleStatus},
+  state::{is_allowed, RAFFLE},
+};
+use cosmwasm_std::{attr, Addr, DepsMut, Env, MessageInfo, Response, Uint128};
+use cw_lib::models::Token;
+
+/// Deposit native token prizes sent in info.funds.
+pub fn fund(
+  mut deps: DepsMut,
+  _env: Env,
+  info: MessageInfo,
+) -> ContractResult<Response> {
+  if info.funds.is_empty() {
+    return Err(ContractError::MissingFunds {});
+  }
+  for coin in info.funds.iter() {
+    fund_token(
+      &mut deps,
+      &info.sender,
+      &Token::Native {
+        denom: coin.denom.clone(),
+      },
+      coin.amount,
+    )?;
+  }
+  Ok(Response::new().add_attributes(vec![attr("action", "fund")]))
+}
+
+/// Deposit a cw20 token prize, sent through the contract's cw20 Receive hook.
+pub fn fund_cw20(
+  mut deps: DepsMut,
+  sender: &Addr,
+  cw20_addr: &Addr,
+  amount: Uint128,
+) -> ContractResult<Response> {
+  fund_token(
+    &mut deps,
+    sender,
+    &Token::Cw20 {
+      address: cw20_addr.clone(),
+    },
+    amount,
+  )?;
+  Ok(Response::new().add_attributes(vec![
+    attr("action", "fund"),
+    attr("amount", amount.to_string()),
+  ]))
+}
+
+fn fund_token(
+  deps: &mut DepsMut,
+  sender: &Addr,
+  token: &Token,
+  amount: Uint128,
+) -> ContractResult<()> {
+  if !is_allowed(&deps.as_ref(), sender, "fund")? {
+    return Err(ContractError::NotAuthorized {});
+  }
+
+  let mut raffle = RAFFLE.load(deps.storage)?;
+
+  if raffle.status != RaffleStatus::Active {
+    return Err(ContractError::NotActive {});
+  }
+
+  raffle.fund(token, amount)?;
+
+  RAFFLE.save(deps.storage, &raffle)?;
+  Ok(())
+}
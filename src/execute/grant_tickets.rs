@@ -0,0 +1,102 @@
+use crate::{
+  error::ContractError,
+  models::{RaffleStatus, TicketOrder, RAFFLE_STAGE_HAS_BUYERS},
+  state::{
+    is_allowed, repository, IX_U64_STATUS, IX_U64_TICKETS_SOLD, IX_U64_WALLET_COUNT, RAFFLE,
+  },
+};
+use cosmwasm_std::{attr, Addr, DepsMut, Env, MessageInfo, Response, Uint128};
+
+use super::buy_tickets::record_order;
+
+/// Grant free tickets to each wallet in `grants`. Free tickets are entered in
+/// the draw like any other but pay nothing, so they are refunded nothing and
+/// add nothing to the proceeds paid out to royalty and fee recipients.
+pub fn grant_tickets(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  grants: Vec<(Addr, u32)>,
+) -> Result<Response, ContractError> {
+  if !is_allowed(&deps.as_ref(), &info.sender, "grant_tickets")? {
+    return Err(ContractError::NotAuthorized {});
+  }
+
+  let mut raffle = RAFFLE.load(deps.storage)?;
+
+  if raffle.status != RaffleStatus::Active {
+    return Err(ContractError::NotActive {});
+  }
+
+  if raffle.is_awaiting_commitment() {
+    return Err(ContractError::CommitmentRequired {});
+  }
+
+  // abort if ticket sales period expired
+  if let Some(deadline) = raffle.ticket_sales_end_at {
+    if env.block.time >= deadline {
+      return Err(ContractError::SalesPeriodOver {});
+    }
+  }
+
+  if grants.is_empty() {
+    return Err(ContractError::ValidationError {
+      reason: Some("no tickets granted".into()),
+    });
+  }
+
+  // abort if there aren't enough tickets left for every grant
+  let total_count = grants
+    .iter()
+    .try_fold(0u32, |total, (_, count)| total.checked_add(*count))
+    .filter(|total| raffle.tickets_sold.checked_add(*total).is_some())
+    .ok_or(ContractError::ValidationError {
+      reason: Some("too many tickets granted".into()),
+    })?;
+  if let Some(ticket_supply) = raffle.ticket_supply {
+    if ticket_supply - raffle.tickets_sold < total_count {
+      return Err(ContractError::SoldOut {});
+    }
+  }
+
+  let mut repo_update = repository(deps.storage)?.update();
+
+  for (addr, count) in grants.iter() {
+    if *count == 0 {
+      return Err(ContractError::ValidationError {
+        reason: Some("grants must be for at least 1 ticket".into()),
+      });
+    }
+    let mut order = TicketOrder {
+      address: deps.api.addr_validate(addr.as_str())?,
+      is_visible: true,
+      count: *count,
+      amount_paid: Some(Uint128::zero()),
+      house_revenue: None,
+      is_free: true,
+      referrer: None,
+      first_ticket: None,
+      last_ticket: None,
+      time: Some(env.block.time),
+    };
+    record_order(deps.storage, &env, &mut raffle, &mut order, None)?;
+    repo_update = repo_update.add_relationship(&order.address, "player");
+  }
+
+  RAFFLE.save(deps.storage, &raffle)?;
+
+  Ok(
+    Response::new()
+      .add_attributes(vec![
+        attr("action", "grant_tickets"),
+        attr("count", total_count.to_string()),
+      ])
+      .add_message(
+        repo_update
+          .set_u64(IX_U64_TICKETS_SOLD, raffle.tickets_sold.into())
+          .set_u64(IX_U64_WALLET_COUNT, raffle.wallet_count.into())
+          .set_u64(IX_U64_STATUS, RAFFLE_STAGE_HAS_BUYERS as u64)
+          .build_msg()?,
+      ),
+  )
+}
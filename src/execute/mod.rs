@@ -1,13 +1,31 @@
 mod buy_tickets;
 mod cancel;
 mod choose_winner;
+mod claim_referral_earnings;
 mod claim_refund;
+mod commit_secret;
+mod fund;
+mod grant_tickets;
+mod nois_receive;
+mod receive;
+mod receive_nft;
+mod sweep;
 mod transfer_ownership;
 mod update;
+mod update_allowlist;
 
 pub use buy_tickets::buy_tickets;
 pub use cancel::cancel;
 pub use choose_winner::choose_winner;
+pub use claim_referral_earnings::claim_referral_earnings;
 pub use claim_refund::claim_refund;
+pub use commit_secret::commit_secret;
+pub use fund::fund;
+pub use grant_tickets::grant_tickets;
+pub use nois_receive::nois_receive;
+pub use receive::receive;
+pub use receive_nft::receive_nft;
+pub use sweep::sweep;
 pub use transfer_ownership::transfer_ownership;
 pub use update::update;
+pub use update_allowlist::update_allowlist;
@@ -0,0 +1,41 @@
+use crate::{
+  error::ContractError,
+  models::{ContractResult, RaffleStatus, RandomnessSource},
+  msg::NoisCallback,
+  state::{BEACON_JOB_ID, RAFFLE},
+};
+use cosmwasm_std::{attr, DepsMut, Env, MessageInfo, Response};
+
+use super::choose_winner::settle_draw;
+
+/// Callback from the randomness beacon's proxy contract, delivering the
+/// randomness requested by `choose_winner`. Winners are drawn here.
+pub fn nois_receive(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  callback: NoisCallback,
+) -> ContractResult<Response> {
+  let raffle = RAFFLE.load(deps.storage)?;
+
+  // only the raffle's configured proxy can deliver randomness
+  match &raffle.randomness_source {
+    RandomnessSource::Beacon { proxy_address } if *proxy_address == info.sender => {},
+    _ => return Err(ContractError::NotAuthorized {}),
+  }
+
+  if raffle.status != RaffleStatus::Drawing {
+    return Err(ContractError::NotActive {});
+  }
+
+  if callback.job_id != BEACON_JOB_ID || callback.randomness.len() != 32 {
+    return Err(ContractError::InvalidRandomness {});
+  }
+
+  Ok(
+    settle_draw(deps, &env, raffle, Some(&callback.randomness))?.add_attributes(vec![
+      attr("action", "nois_receive"),
+      attr("randomness", callback.randomness.to_hex()),
+    ]),
+  )
+}
@@ -0,0 +1,40 @@
+use crate::{models::ContractResult, msg::ReceiveMsg};
+use cosmwasm_std::{from_binary, DepsMut, Env, MessageInfo, Response};
+use cw20::Cw20ReceiveMsg;
+
+use super::{buy_tickets::buy_tickets_cw20, fund::fund_cw20};
+
+/// Callback from a cw20 token contract, executed when tokens are sent to this
+/// contract. info.sender is the cw20 token contract itself.
+pub fn receive(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  msg: Cw20ReceiveMsg,
+) -> ContractResult<Response> {
+  match from_binary(&msg.msg)? {
+    ReceiveMsg::Fund {} => {
+      let sender = deps.api.addr_validate(&msg.sender)?;
+      fund_cw20(deps, &sender, &info.sender, msg.amount)
+    },
+    ReceiveMsg::BuyTickets {
+      count,
+      message,
+      is_visible,
+      proof,
+      allocation,
+      referrer,
+    } => buy_tickets_cw20(
+      deps,
+      env,
+      &info.sender,
+      &msg,
+      count,
+      message,
+      is_visible,
+      proof,
+      allocation,
+      referrer,
+    ),
+  }
+}
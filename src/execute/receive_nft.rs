@@ -0,0 +1,53 @@
+use crate::{
+  error::ContractError,
+  models::{ContractResult, RaffleAsset, RaffleStatus},
+  state::{is_allowed, RAFFLE},
+};
+use cosmwasm_std::{attr, DepsMut, Env, MessageInfo, Response};
+use cw721::Cw721ReceiveMsg;
+
+/// Callback from a cw721 collection contract, executed when an NFT is sent to
+/// this contract. The NFT must correspond to one of the raffle's NFT assets.
+pub fn receive_nft(
+  deps: DepsMut,
+  _env: Env,
+  info: MessageInfo,
+  msg: Cw721ReceiveMsg,
+) -> ContractResult<Response> {
+  let sender = deps.api.addr_validate(&msg.sender)?;
+  if !is_allowed(&deps.as_ref(), &sender, "escrow_nft")? {
+    return Err(ContractError::NotAuthorized {});
+  }
+
+  let mut raffle = RAFFLE.load(deps.storage)?;
+
+  if raffle.status != RaffleStatus::Active {
+    return Err(ContractError::NotActive {});
+  }
+
+  // info.sender is the cw721 contract that executed this callback, so the
+  // NFT is only marked as escrowed if the collection address matches it.
+  let asset = raffle.assets.iter_mut().find(|asset| match asset {
+    RaffleAsset::Nft {
+      collection_address,
+      token_id,
+      is_escrowed,
+      ..
+    } => !*is_escrowed && *collection_address == info.sender && *token_id == msg.token_id,
+    _ => false,
+  });
+
+  if let Some(RaffleAsset::Nft { is_escrowed, .. }) = asset {
+    *is_escrowed = true;
+  } else {
+    return Err(ContractError::InvalidNft {});
+  }
+
+  RAFFLE.save(deps.storage, &raffle)?;
+
+  Ok(Response::new().add_attributes(vec![
+    attr("action", "receive_nft"),
+    attr("collection_address", info.sender.to_string()),
+    attr("token_id", msg.token_id),
+  ]))
+}
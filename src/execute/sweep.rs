@@ -0,0 +1,78 @@
+use crate::{
+  error::ContractError,
+  models::{ContractResult, RaffleStatus},
+  state::{is_allowed, RAFFLE, REFUND_GRACE_PERIOD_SECONDS},
+};
+use cosmwasm_std::{attr, Addr, DepsMut, Env, MessageInfo, Response, Uint128};
+use cw_lib::{
+  models::Token,
+  utils::funds::{build_cw20_transfer_submsg, build_send_msg, get_token_balance},
+};
+
+/// Transfer tokens left in the contract after the raffle has ended, like
+/// rounding dust, overpayments or tokens sent by mistake. Funds still owed to
+/// refund claimants or referrers are never swept.
+pub fn sweep(
+  deps: DepsMut,
+  env: Env,
+  info: MessageInfo,
+  token: Token,
+  recipient: Addr,
+) -> ContractResult<Response> {
+  if !is_allowed(&deps.as_ref(), &info.sender, "sweep")? {
+    return Err(ContractError::NotAuthorized {});
+  }
+
+  let recipient = deps.api.addr_validate(recipient.as_str())?;
+  let raffle = RAFFLE.load(deps.storage)?;
+
+  // amount of the token that must stay in the contract
+  let reserved_amount = match raffle.status {
+    RaffleStatus::Active | RaffleStatus::Drawing => return Err(ContractError::NotEnded {}),
+    RaffleStatus::Complete => {
+      if token == raffle.price.token {
+        raffle.referral_earnings
+      } else {
+        Uint128::zero()
+      }
+    },
+    RaffleStatus::Canceled | RaffleStatus::Failed => {
+      let unclaimed_refunds = raffle.refunds_outstanding;
+      if !unclaimed_refunds.is_zero() {
+        // wait for all refunds to be claimed or for the grace period to end
+        let is_grace_period_over = raffle
+          .canceled_at
+          .map(|t| env.block.time >= t.plus_seconds(REFUND_GRACE_PERIOD_SECONDS))
+          .unwrap_or(false);
+        if !is_grace_period_over {
+          return Err(ContractError::RefundsPending {});
+        }
+      }
+      if token == raffle.price.token {
+        unclaimed_refunds
+      } else {
+        Uint128::zero()
+      }
+    },
+  };
+
+  let balance = get_token_balance(deps.querier, &env.contract.address, &token)?;
+  let amount = balance.saturating_sub(reserved_amount);
+
+  let mut resp = Response::new().add_attributes(vec![
+    attr("action", "sweep"),
+    attr("recipient", recipient.to_string()),
+    attr("amount", amount.to_string()),
+  ]);
+
+  if !amount.is_zero() {
+    resp = match &token {
+      Token::Native { denom } => resp.add_message(build_send_msg(&recipient, denom, amount)?),
+      Token::Cw20 { address: cw20_addr } => {
+        resp.add_submessage(build_cw20_transfer_submsg(&recipient, cw20_addr, amount)?)
+      },
+    };
+  }
+
+  Ok(resp)
+}
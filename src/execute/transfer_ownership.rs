@@ -1,6 +1,6 @@
 use crate::{
   error::ContractError,
-  state::{is_owner, repository, IX_STR_OWNER, RAFFLE_OWNER},
+  state::{is_owner, repository, FEE_CONFIG, IX_STR_OWNER, RAFFLE_OWNER},
 };
 use cosmwasm_std::{attr, Addr, DepsMut, Env, MessageInfo, Response};
 
@@ -14,6 +14,16 @@ pub fn transfer_ownership(
     return Err(ContractError::NotAuthorized {});
   }
   RAFFLE_OWNER.save(deps.storage, new_owner)?;
+
+  // fees paid to the owner, like the raffle creator's fee of legacy raffles,
+  // go to whoever owns the raffle when winners are drawn
+  let mut fees = FEE_CONFIG.load(deps.storage)?;
+  for recipient in fees.recipients.iter_mut() {
+    if recipient.address == info.sender {
+      recipient.address = new_owner.clone();
+    }
+  }
+  FEE_CONFIG.save(deps.storage, &fees)?;
   Ok(
     Response::new()
       .add_attributes(vec![
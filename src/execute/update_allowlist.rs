@@ -0,0 +1,24 @@
+use crate::{
+  error::ContractError,
+  state::{is_allowed, ALLOWLIST},
+};
+use cosmwasm_std::{attr, Addr, DepsMut, Env, MessageInfo, Response};
+
+pub fn update_allowlist(
+  deps: DepsMut,
+  _env: Env,
+  info: MessageInfo,
+  add: Option<Vec<Addr>>,
+  remove: Option<Vec<Addr>>,
+) -> Result<Response, ContractError> {
+  if !is_allowed(&deps.as_ref(), &info.sender, "update_allowlist")? {
+    return Err(ContractError::NotAuthorized {});
+  }
+  for addr in add.unwrap_or_default().iter() {
+    ALLOWLIST.save(deps.storage, deps.api.addr_validate(addr.as_str())?, &())?;
+  }
+  for addr in remove.unwrap_or_default().iter() {
+    ALLOWLIST.remove(deps.storage, addr.clone());
+  }
+  Ok(Response::new().add_attributes(vec![attr("action", "update_allowlist")]))
+}
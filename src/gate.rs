@@ -0,0 +1,72 @@
+use cosmwasm_std::{Addr, Deps};
+use cw721::{Cw721QueryMsg, TokensResponse};
+use cw_lib::{
+  models::{Token, TokenAmount},
+  utils::funds::get_token_balance,
+};
+
+use crate::{
+  models::{ContractResult, Raffle, SaleGate},
+  state::ALLOWLIST,
+};
+
+/// Check whether the wallet passes the raffle's sale gate, returning the
+/// reason why not if it doesn't. If the wallet has already transferred
+/// `payment` to the contract in this transaction, it counts towards the
+/// wallet's balance.
+pub fn check_eligibility(
+  deps: Deps,
+  raffle: &Raffle,
+  wallet: &Addr,
+  payment: Option<&TokenAmount>,
+) -> ContractResult<Option<String>> {
+  match &raffle.sale_gate {
+    None => Ok(None),
+    Some(SaleGate::Allowlist {}) => {
+      if ALLOWLIST.has(deps.storage, wallet.clone()) {
+        Ok(None)
+      } else {
+        Ok(Some("wallet is not on the allowlist".into()))
+      }
+    },
+    Some(SaleGate::Cw721Holder { collection_address }) => {
+      let resp: TokensResponse = deps.querier.query_wasm_smart(
+        collection_address,
+        &Cw721QueryMsg::Tokens {
+          owner: wallet.to_string(),
+          start_after: None,
+          limit: Some(1),
+        },
+      )?;
+      if resp.tokens.is_empty() {
+        Ok(Some(format!(
+          "wallet holds no NFT in collection {}",
+          collection_address
+        )))
+      } else {
+        Ok(None)
+      }
+    },
+    Some(SaleGate::Cw20Balance {
+      address,
+      min_amount,
+    }) => {
+      let token = Token::Cw20 {
+        address: address.clone(),
+      };
+      let balance = get_token_balance(deps.querier, wallet, &token)?;
+      let paid = payment
+        .filter(|payment| payment.token == token)
+        .map(|payment| payment.amount)
+        .unwrap_or_default();
+      if balance + paid < *min_amount {
+        Ok(Some(format!(
+          "wallet holds less than {} of token {}",
+          min_amount, address
+        )))
+      } else {
+        Ok(None)
+      }
+    },
+  }
+}
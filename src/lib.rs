@@ -1,8 +1,12 @@
 pub mod contract;
 mod error;
 pub mod execute;
+mod gate;
+pub mod merkle;
 pub mod models;
 pub mod msg;
+mod nft;
+mod payout;
 pub mod query;
 mod selection;
 pub mod state;
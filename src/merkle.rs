@@ -0,0 +1,139 @@
+//! Merkle proofs for presale allowlists. A leaf is the sha256 hash of the
+//! buyer's address or, if the buyer has a ticket allocation, of
+//! "{address}:{allocation}". Parent nodes hash the concatenation of their two
+//! children, sorted bytewise, so proofs don't need to encode sibling order.
+//! Roots and proof nodes are hex-encoded.
+use sha2::{Digest, Sha256};
+
+use crate::{error::ContractError, models::ContractResult};
+
+/// Build the leaf hash for an address and optional ticket allocation.
+pub fn build_leaf(
+  address: &str,
+  allocation: Option<u32>,
+) -> [u8; 32] {
+  let preimage = match allocation {
+    Some(n) => format!("{}:{}", address, n),
+    None => address.to_string(),
+  };
+  Sha256::digest(preimage.as_bytes()).into()
+}
+
+/// Hash two sibling nodes into their parent node.
+pub fn hash_pair(
+  a: &[u8; 32],
+  b: &[u8; 32],
+) -> [u8; 32] {
+  let mut hasher = Sha256::new();
+  if a <= b {
+    hasher.update(a);
+    hasher.update(b);
+  } else {
+    hasher.update(b);
+    hasher.update(a);
+  }
+  hasher.finalize().into()
+}
+
+/// Return true if the proof leads from the leaf to the hex-encoded root.
+pub fn verify_proof(
+  root: &str,
+  leaf: [u8; 32],
+  proof: &[String],
+) -> ContractResult<bool> {
+  let root = decode_node(root)?;
+  let mut node = leaf;
+  for sibling in proof.iter() {
+    node = hash_pair(&node, &decode_node(sibling)?);
+  }
+  Ok(node == root)
+}
+
+fn decode_node(hex_str: &str) -> ContractResult<[u8; 32]> {
+  let mut node = [0u8; 32];
+  hex::decode_to_slice(hex_str, &mut node).map_err(|_| ContractError::InvalidMerkleProof {})?;
+  Ok(node)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // tree over four leaves, two of which have a ticket allocation:
+  //
+  //                  root
+  //           /              \
+  //        n01                n23
+  //      /     \            /     \
+  //  alice   bob:5      carol    dave:2
+  const ROOT: &str = "49ab69948fc0df8cbc19b2f18c15984824085e77c47adac11294f3ea00281ae1";
+
+  fn leaves() -> [[u8; 32]; 4] {
+    [
+      build_leaf("juno1alice", None),
+      build_leaf("juno1bob", Some(5)),
+      build_leaf("juno1carol", None),
+      build_leaf("juno1dave", Some(2)),
+    ]
+  }
+
+  fn proof_for(index: usize) -> Vec<String> {
+    let leaves = leaves();
+    let n01 = hash_pair(&leaves[0], &leaves[1]);
+    let n23 = hash_pair(&leaves[2], &leaves[3]);
+    let (sibling, uncle) = match index {
+      0 => (leaves[1], n23),
+      1 => (leaves[0], n23),
+      2 => (leaves[3], n01),
+      _ => (leaves[2], n01),
+    };
+    vec![hex::encode(sibling), hex::encode(uncle)]
+  }
+
+  #[test]
+  fn leaf_hashes_address_and_allocation() {
+    assert_eq!(
+      hex::encode(build_leaf("juno1bob", Some(5))),
+      "d5e3c192b0a9ffdb44aab728ceb26381c3f83b765a52a8750408e31d0c6d6754"
+    );
+    assert_ne!(
+      build_leaf("juno1bob", Some(5)),
+      build_leaf("juno1bob", None)
+    );
+  }
+
+  #[test]
+  fn valid_proofs_verify() {
+    let leaves = leaves();
+    for (i, leaf) in leaves.iter().enumerate() {
+      assert!(verify_proof(ROOT, *leaf, &proof_for(i)).unwrap());
+    }
+  }
+
+  #[test]
+  fn tampered_sibling_fails() {
+    let mut proof = proof_for(0);
+    proof[0].replace_range(0..2, "00");
+    assert!(!verify_proof(ROOT, leaves()[0], &proof).unwrap());
+  }
+
+  #[test]
+  fn wrong_allocation_fails() {
+    let proof = proof_for(1);
+    assert!(!verify_proof(ROOT, build_leaf("juno1bob", Some(6)), &proof).unwrap());
+    assert!(!verify_proof(ROOT, build_leaf("juno1bob", None), &proof).unwrap());
+  }
+
+  #[test]
+  fn malformed_nodes_are_rejected() {
+    let mut proof = proof_for(0);
+    proof[1] = "not hex".to_string();
+    assert!(verify_proof(ROOT, leaves()[0], &proof).is_err());
+
+    let mut proof = proof_for(0);
+    proof[1].truncate(62);
+    assert!(verify_proof(ROOT, leaves()[0], &proof).is_err());
+
+    assert!(verify_proof(&ROOT[..62], leaves()[0], &proof_for(0)).is_err());
+  }
+}
@@ -1,9 +1,10 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Timestamp, Uint128};
+use cosmwasm_std::{Addr, HexBinary, Timestamp, Uint128};
 use cw_lib::models::{Token, TokenAmount};
 
 use crate::error::ContractError;
 
+pub const RAFFLE_STAGE_UPCOMING: u8 = 4;
 pub const RAFFLE_STAGE_HAS_BUYERS: u8 = 3;
 pub const RAFFLE_STAGE_ACTIVE: u8 = 2;
 pub const RAFFLE_STAGE_COMPLETED: u8 = 1;
@@ -14,8 +15,43 @@ pub type ContractResult<T> = Result<T, ContractError>;
 #[cw_serde]
 pub enum RaffleStatus {
   Active,
+  /// Waiting on the randomness beacon to call back with the draw's entropy.
+  Drawing,
   Complete,
   Canceled,
+  Failed,
+}
+
+/// Determines what is removed from the ticket bag after each winner is drawn
+/// in a multi-winner raffle.
+#[cw_serde]
+#[derive(Default)]
+pub enum SelectionMode {
+  /// Remove all of the winning wallet's tickets, so a wallet wins at most once.
+  #[default]
+  Wallet,
+  /// Remove only the drawn ticket, so a wallet wins at most once per ticket.
+  Ticket,
+}
+
+/// Source of the entropy used to draw winners.
+#[cw_serde]
+#[derive(Default)]
+pub enum RandomnessSource {
+  /// Seed the draw from buyer-contributed entropy and block data.
+  #[default]
+  Block,
+  /// Request randomness from a Nois-compatible proxy contract. Winners are
+  /// drawn when the proxy calls back with a 32-byte beacon.
+  Beacon { proxy_address: Addr },
+  /// Mix a secret revealed by the owner with buyer-contributed entropy. The
+  /// owner commits to the hex-encoded sha256 hash of the secret before the
+  /// first ticket is issued and reveals the secret in `ChooseWinner` before
+  /// the deadline.
+  CommitReveal {
+    commitment: Option<String>,
+    reveal_deadline: Timestamp,
+  },
 }
 
 #[cw_serde]
@@ -25,6 +61,68 @@ pub struct RoyaltyRecipient {
   pub pct: u8,
 }
 
+/// Platform fee taken from ticket sale proceeds, in basis points of the pot.
+#[cw_serde]
+pub struct FeeRecipient {
+  pub name: Option<String>,
+  pub address: Addr,
+  pub bps: u16,
+}
+
+/// Share of each ticket purchase made in the given token that is forwarded to
+/// a house staking contract at the time of purchase.
+#[cw_serde]
+pub struct HouseRevenue {
+  pub token: Token,
+  pub house_address: Addr,
+  pub bps: u16,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct FeeConfig {
+  pub recipients: Vec<FeeRecipient>,
+  pub house_revenue: Vec<HouseRevenue>,
+  pub remainder_address: Option<Addr>,
+  /// Share of each referred purchase, in basis points, earned by the referrer.
+  #[serde(default)]
+  pub referral_bps: u16,
+}
+
+impl FeeConfig {
+  /// Total basis points of the pot paid out as platform fees.
+  pub fn total_bps(&self) -> u128 {
+    self.recipients.iter().map(|r| r.bps as u128).sum()
+  }
+
+  /// Return the house revenue rule for the given token, if any.
+  pub fn get_house_revenue(
+    &self,
+    token: &Token,
+  ) -> Option<&HouseRevenue> {
+    self.house_revenue.iter().find(|x| x.token == *token)
+  }
+}
+
+/// Discount applied to orders of at least `min_count` tickets.
+#[cw_serde]
+pub struct PriceTier {
+  pub min_count: u32,
+  pub discount_bps: u16,
+}
+
+/// Restricts ticket sales to wallets meeting the given condition.
+#[cw_serde]
+pub enum SaleGate {
+  /// Wallet must hold at least one NFT in the collection.
+  Cw721Holder { collection_address: Addr },
+  /// Wallet must hold at least the given balance of the cw20 token. When
+  /// tickets are paid in the same token, the balance is checked before payment.
+  Cw20Balance { address: Addr, min_amount: Uint128 },
+  /// Wallet must be on the raffle's allowlist.
+  Allowlist {},
+}
+
 #[cw_serde]
 pub enum Network {
   Stargaze,
@@ -32,6 +130,26 @@ pub enum Network {
   Juno,
 }
 
+impl Network {
+  pub fn bech32_prefix(&self) -> &'static str {
+    match self {
+      Network::Stargaze => "stars",
+      Network::Teritori => "tori",
+      Network::Juno => "juno",
+    }
+  }
+
+  /// Return true if the network is the chain hosting the given contract.
+  pub fn is_host(
+    &self,
+    contract_address: &Addr,
+  ) -> bool {
+    contract_address
+      .as_str()
+      .starts_with(&format!("{}1", self.bech32_prefix()))
+  }
+}
+
 #[cw_serde]
 pub struct RaffleStyle {
   ui_base_color: String,
@@ -62,6 +180,9 @@ pub enum RaffleAsset {
     token: Token,
     amount: Uint128,
     terms: Option<String>,
+    rank: Option<u8>,
+    #[serde(default)]
+    funded_amount: Uint128,
   },
   Nft {
     network: Network,
@@ -70,6 +191,13 @@ pub enum RaffleAsset {
     token_id: String,
     terms: Option<String>,
     image_url: Option<String>,
+    rank: Option<u8>,
+    #[serde(default)]
+    is_escrowed: bool,
+    /// True if the NFT lives on the contract's own chain and must be escrowed
+    /// before ticket sales open. NFTs on other chains are handled off-chain.
+    #[serde(default)]
+    requires_escrow: bool,
   },
   Asset {
     name: String,
@@ -78,14 +206,43 @@ pub enum RaffleAsset {
     image: Option<String>,
     address: Option<Addr>,
     terms: Option<String>,
+    rank: Option<u8>,
   },
 }
 
+impl RaffleAsset {
+  /// Prize rank the asset is awarded to, where 1 is first place. Assets
+  /// without an explicit rank go to the first place winner.
+  pub fn rank(&self) -> u8 {
+    match self {
+      RaffleAsset::Token { rank, .. } => rank.unwrap_or(1),
+      RaffleAsset::Nft { rank, .. } => rank.unwrap_or(1),
+      RaffleAsset::Asset { rank, .. } => rank.unwrap_or(1),
+    }
+  }
+}
+
 #[cw_serde]
 pub struct TicketOrder {
   pub address: Addr,
   pub count: u32,
   pub is_visible: bool,
+  pub amount_paid: Option<Uint128>,
+  pub house_revenue: Option<Uint128>,
+  #[serde(default)]
+  pub is_free: bool,
+  pub referrer: Option<Addr>,
+  pub first_ticket: Option<u32>,
+  pub last_ticket: Option<u32>,
+  pub time: Option<Timestamp>,
+}
+
+/// Inclusive range of ticket numbers held from a single order. Tickets are
+/// numbered from 0 in purchase order.
+#[cw_serde]
+pub struct TicketRange {
+  pub first_ticket: u32,
+  pub last_ticket: u32,
 }
 
 #[cw_serde]
@@ -94,10 +251,34 @@ pub struct Raffle {
   pub assets: Vec<RaffleAsset>,
   pub status: RaffleStatus,
   pub ticket_supply: Option<u32>,
+  pub ticket_sales_start_at: Option<Timestamp>,
   pub ticket_sales_end_at: Option<Timestamp>,
   pub ticket_sales_target: Option<u32>,
+  pub max_tickets_per_wallet: Option<u32>,
+  pub max_tickets_per_order: Option<u32>,
+  pub sale_gate: Option<SaleGate>,
+  pub merkle_root: Option<String>,
+  pub price_tiers: Option<Vec<PriceTier>>,
+  pub canceled_at: Option<Timestamp>,
   pub winner_address: Option<Addr>,
+  #[serde(default)]
+  pub winners: Vec<(u8, Addr)>,
+  #[serde(default)]
+  pub selection_mode: SelectionMode,
+  #[serde(default)]
+  pub randomness_source: RandomnessSource,
+  pub draw_requested_at: Option<Timestamp>,
+  /// Referral earnings accrued by referrers and not yet claimed.
+  #[serde(default)]
+  pub referral_earnings: Uint128,
+  /// Ticket payments, net of house revenue, not yet refunded to buyers. This
+  /// is what buyers are owed if the raffle is canceled.
+  #[serde(default)]
+  pub refunds_outstanding: Uint128,
   pub tickets_sold: u32,
+  /// Number of tickets sold that were granted for free.
+  #[serde(default)]
+  pub free_tickets: u32,
   pub wallet_count: u32,
   pub seed: String,
 }
@@ -109,6 +290,176 @@ impl Raffle {
     }
     return false;
   }
+
+  /// Return the discount for an order of the given size, in basis points,
+  /// using the best price tier the order qualifies for.
+  pub fn get_discount_bps(
+    &self,
+    count: u32,
+  ) -> u16 {
+    self
+      .price_tiers
+      .iter()
+      .flatten()
+      .filter(|tier| count >= tier.min_count)
+      .map(|tier| tier.discount_bps)
+      .max()
+      .unwrap_or(0)
+  }
+
+  /// Total amount due for an order of the given number of tickets.
+  pub fn get_order_price(
+    &self,
+    count: u32,
+  ) -> Uint128 {
+    let full_price = Uint128::from(count) * self.price.amount;
+    let discount = full_price.multiply_ratio(self.get_discount_bps(count), 10_000u128);
+    full_price - discount
+  }
+
+  /// Max number of tickets a wallet holding the given number of tickets can
+  /// still buy in one order, or None if unlimited.
+  pub fn get_ticket_allowance(
+    &self,
+    wallet_ticket_count: u32,
+  ) -> Option<u32> {
+    [
+      self
+        .max_tickets_per_wallet
+        .map(|n| n.saturating_sub(wallet_ticket_count)),
+      self.max_tickets_per_order,
+      self
+        .ticket_supply
+        .map(|n| n.saturating_sub(self.tickets_sold)),
+    ]
+    .iter()
+    .filter_map(|x| *x)
+    .min()
+  }
+
+  /// Return true if ticket sales are scheduled to open after the given time.
+  pub fn is_upcoming(
+    &self,
+    time: Timestamp,
+  ) -> bool {
+    self.status == RaffleStatus::Active
+      && self
+        .ticket_sales_start_at
+        .map(|t| time < t)
+        .unwrap_or(false)
+  }
+
+  /// Return true if the raffle draws from a commit-reveal secret that the
+  /// owner hasn't committed to yet. No tickets are issued until they do.
+  pub fn is_awaiting_commitment(&self) -> bool {
+    matches!(
+      self.randomness_source,
+      RandomnessSource::CommitReveal {
+        commitment: None,
+        ..
+      }
+    )
+  }
+
+  /// Number of tickets sold that were paid for.
+  pub fn get_paid_ticket_count(&self) -> u32 {
+    self.tickets_sold - self.free_tickets
+  }
+
+  /// Return true if ticket holders can claim refunds.
+  pub fn is_refundable(&self) -> bool {
+    self.status == RaffleStatus::Canceled || self.status == RaffleStatus::Failed
+  }
+
+  /// Return true if every NFT asset requiring escrow has been transferred to
+  /// the contract.
+  pub fn is_escrow_complete(&self) -> bool {
+    self.assets.iter().all(|a| match a {
+      RaffleAsset::Nft {
+        is_escrowed,
+        requires_escrow,
+        ..
+      } => *is_escrowed || !*requires_escrow,
+      _ => true,
+    })
+  }
+
+  /// Return true if the contract holds the full amount of every token asset.
+  pub fn is_funded(&self) -> bool {
+    self.assets.iter().all(|a| match a {
+      RaffleAsset::Token {
+        amount,
+        funded_amount,
+        ..
+      } => funded_amount >= amount,
+      _ => true,
+    })
+  }
+
+  /// Allocate a deposit to the raffle's underfunded token assets of the same
+  /// token, in order. Deposits exceeding the amount still owed are rejected.
+  pub fn fund(
+    &mut self,
+    token: &Token,
+    amount: Uint128,
+  ) -> ContractResult<()> {
+    let mut remaining = amount;
+    for asset in self.assets.iter_mut() {
+      if let RaffleAsset::Token {
+        token: asset_token,
+        amount: required_amount,
+        funded_amount,
+        ..
+      } = asset
+      {
+        if asset_token == token && *funded_amount < *required_amount {
+          let delta = std::cmp::min(remaining, *required_amount - *funded_amount);
+          *funded_amount += delta;
+          remaining -= delta;
+        }
+      }
+    }
+    if !remaining.is_zero() {
+      return Err(ContractError::ExcessFunds {});
+    }
+    Ok(())
+  }
+
+  /// Total amount of the given token held by the contract for prizes.
+  pub fn get_funded_amount(
+    &self,
+    token: &Token,
+  ) -> Uint128 {
+    self
+      .assets
+      .iter()
+      .map(|a| match a {
+        RaffleAsset::Token {
+          token: asset_token,
+          funded_amount,
+          ..
+        } if asset_token == token => *funded_amount,
+        _ => Uint128::zero(),
+      })
+      .sum()
+  }
+
+  /// Number of distinct prize ranks, i.e. the number of winners to draw.
+  pub fn rank_count(&self) -> u8 {
+    self.assets.iter().map(|a| a.rank()).max().unwrap_or(1)
+  }
+
+  /// Return the winner of the given prize rank, if drawn.
+  pub fn get_winner(
+    &self,
+    rank: u8,
+  ) -> Option<&Addr> {
+    self
+      .winners
+      .iter()
+      .find(|(r, _)| *r == rank)
+      .map(|(_, addr)| addr)
+  }
 }
 
 #[cw_serde]
@@ -125,12 +476,81 @@ pub struct RaffleMarketingInfo {
   pub terms: Option<String>,
 }
 
+/// A winning ticket. The RNG's next value, modulo the number of tickets still
+/// in the draw, gives the index of the winning ticket among them.
+#[cw_serde]
+pub struct DrawnTicket {
+  pub rank: u8,
+  pub random_value: u64,
+  pub tickets_remaining: u32,
+  pub ticket_index: u32,
+  pub ticket_number: u32,
+  pub winner: Addr,
+}
+
+/// Everything needed to reproduce a raffle's draw off-chain. The RNG is seeded
+/// from `seed` and `entropy` if present, or else from `seed`, block time in
+/// nanoseconds, block height and transaction index.
+#[cw_serde]
+pub struct DrawRecord {
+  pub seed: String,
+  pub block_time: Timestamp,
+  pub block_height: u64,
+  pub tx_index: u64,
+  pub entropy: Option<HexBinary>,
+  pub ordering: String,
+  pub selection_mode: SelectionMode,
+  pub total_tickets: u32,
+  pub draws: Vec<DrawnTicket>,
+}
+
+/// Purchases attributed to a referrer and the referrer's share of them.
+#[cw_serde]
+pub struct ReferralEarnings {
+  pub volume: Uint128,
+  pub order_count: u32,
+  pub ticket_count: u32,
+  pub earnings: Uint128,
+  pub has_claimed: bool,
+}
+
 #[cw_serde]
 pub struct WalletMetadata {
   pub has_agreed_to_terms: bool,
   pub ticket_order_count: u16,
   pub ticket_count: u32,
+  #[serde(default)]
+  pub free_ticket_count: u32,
+  #[serde(default)]
+  pub allocation_used: u32,
+  pub amount_paid: Option<Uint128>,
+  pub house_revenue: Option<Uint128>,
+  pub last_ordered_at: Option<Timestamp>,
   pub address: Option<Addr>,
   pub display_message: Option<String>,
   pub has_claimed_refund: bool,
 }
+
+impl WalletMetadata {
+  /// Total amount paid for the wallet's tickets. Wallets from before amounts
+  /// were recorded paid full price for every ticket.
+  pub fn get_amount_paid(
+    &self,
+    price: Uint128,
+  ) -> Uint128 {
+    self
+      .amount_paid
+      .unwrap_or(Uint128::from(self.ticket_count) * price)
+  }
+
+  /// Amount refundable to the wallet: what it paid, less the house's share,
+  /// which was forwarded to the house at the time of purchase.
+  pub fn get_refund_amount(
+    &self,
+    price: Uint128,
+  ) -> Uint128 {
+    self
+      .get_amount_paid(price)
+      .saturating_sub(self.house_revenue.unwrap_or_default())
+  }
+}
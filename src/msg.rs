@@ -1,10 +1,13 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Timestamp};
-use cw_lib::models::TokenAmount;
+use cosmwasm_std::{Addr, HexBinary, Timestamp, Uint128};
+use cw20::Cw20ReceiveMsg;
+use cw721::Cw721ReceiveMsg;
+use cw_lib::models::{Token, TokenAmount};
 
 use crate::models::{
-  Raffle, RaffleAsset, RaffleMarketingInfo, RaffleStyle, RoyaltyRecipient, SocialMediaUrl,
-  TicketOrder, WalletMetadata,
+  DrawRecord, FeeConfig, PriceTier, Raffle, RaffleAsset, RaffleMarketingInfo, RaffleStyle,
+  RandomnessSource, RoyaltyRecipient, SaleGate, SelectionMode, SocialMediaUrl, TicketOrder,
+  TicketRange, WalletMetadata,
 };
 
 #[cw_serde]
@@ -12,8 +15,15 @@ pub struct InstantiateMsg {
   pub owner: Addr,
   pub acl_address: Option<Addr>,
   pub ticket_supply: Option<u32>,
+  pub ticket_sales_start_at: Option<Timestamp>,
   pub ticket_sales_end_at: Option<Timestamp>,
   pub ticket_sales_target: Option<u32>,
+  pub max_tickets_per_wallet: Option<u32>,
+  pub max_tickets_per_order: Option<u32>,
+  pub sale_gate: Option<SaleGate>,
+  pub allowlist: Option<Vec<Addr>>,
+  pub merkle_root: Option<String>,
+  pub price_tiers: Option<Vec<PriceTier>>,
   pub royalties: Vec<RoyaltyRecipient>,
   pub raffle_name: String,
   pub org_name: Option<String>,
@@ -27,6 +37,9 @@ pub struct InstantiateMsg {
   pub assets: Vec<RaffleAsset>,
   pub price: TokenAmount,
   pub style: RaffleStyle,
+  pub selection_mode: Option<SelectionMode>,
+  pub randomness_source: Option<RandomnessSource>,
+  pub fee_config: Option<FeeConfig>,
 }
 
 #[cw_serde]
@@ -38,13 +51,67 @@ pub enum ExecuteMsg {
     count: u32,
     message: Option<String>,
     is_visible: bool,
+    proof: Option<Vec<String>>,
+    allocation: Option<u32>,
+    referrer: Option<Addr>,
+  },
+  ChooseWinner {
+    secret: Option<String>,
+  },
+  CommitSecret {
+    commitment: String,
   },
-  ChooseWinner {},
   Cancel {},
   ClaimRefund {},
+  ClaimReferralEarnings {},
   Update {
     marketing: Option<RaffleMarketingInfo>,
   },
+  ReceiveNft(Cw721ReceiveMsg),
+  Receive(Cw20ReceiveMsg),
+  Fund {},
+  Sweep {
+    token: Token,
+    recipient: Addr,
+  },
+  UpdateAllowlist {
+    add: Option<Vec<Addr>>,
+    remove: Option<Vec<Addr>>,
+  },
+  GrantTickets {
+    grants: Vec<(Addr, u32)>,
+  },
+  NoisReceive {
+    callback: NoisCallback,
+  },
+}
+
+/// Randomness delivered by a Nois-compatible proxy in its callback.
+#[cw_serde]
+pub struct NoisCallback {
+  pub job_id: String,
+  pub published: Timestamp,
+  pub randomness: HexBinary,
+}
+
+/// Execute msgs understood by Nois-compatible randomness proxy contracts.
+#[cw_serde]
+pub enum ProxyExecuteMsg {
+  GetNextRandomness { job_id: String },
+}
+
+/// Hook msgs embedded in a cw20 `Send` to this contract.
+#[cw_serde]
+pub enum ReceiveMsg {
+  Fund {},
+  BuyTickets {
+    count: u32,
+    message: Option<String>,
+    is_visible: bool,
+    proof: Option<Vec<String>>,
+    allocation: Option<u32>,
+    referrer: Option<Addr>,
+  },
 }
 
 #[cw_serde]
@@ -57,6 +124,22 @@ pub enum QueryMsg {
     claimant: Addr,
   },
   Random {},
+  FundingStatus {},
+  PayoutPreview {},
+  Eligibility {
+    wallet: Addr,
+  },
+  QuotePrice {
+    count: u32,
+    wallet: Option<Addr>,
+  },
+  Referrals {
+    referrer: Addr,
+  },
+  DrawProof {},
+  Tickets {
+    wallet: Addr,
+  },
 }
 
 #[cw_serde]
@@ -73,6 +156,9 @@ pub struct SelectResponse {
   pub orders: Option<Vec<TicketOrder>>,
   pub wallets: Option<Vec<WalletMetadata>>,
   pub royalties: Option<Vec<RoyaltyRecipient>>,
+  pub fees: Option<FeeConfig>,
+  pub ticket_allowance: Option<u32>,
+  pub tickets: Option<Vec<TicketRange>>,
 }
 
 #[cw_serde]
@@ -84,3 +170,65 @@ pub struct RefundStatusResponse {
 pub struct RandomResponse {
   pub results: Vec<(Addr, u32)>,
 }
+
+#[cw_serde]
+pub struct AssetFundingStatus {
+  pub index: u32,
+  pub token: Token,
+  pub amount: Uint128,
+  pub funded_amount: Uint128,
+}
+
+#[cw_serde]
+pub struct FundingStatusResponse {
+  pub is_funded: bool,
+  pub assets: Vec<AssetFundingStatus>,
+}
+
+#[cw_serde]
+pub struct Payout {
+  pub address: Addr,
+  pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct PayoutPreviewResponse {
+  pub token: Token,
+  pub total: Uint128,
+  pub payouts: Vec<Payout>,
+}
+
+#[cw_serde]
+pub struct EligibilityResponse {
+  pub is_eligible: bool,
+  pub reason: Option<String>,
+}
+
+#[cw_serde]
+pub struct QuotePriceResponse {
+  pub token: Token,
+  pub amount: Uint128,
+  pub discount_bps: u16,
+  pub ticket_allowance: Option<u32>,
+}
+
+#[cw_serde]
+pub struct ReferralsResponse {
+  pub referrer: Addr,
+  pub volume: Uint128,
+  pub order_count: u32,
+  pub ticket_count: u32,
+  pub earnings: Uint128,
+  pub has_claimed: bool,
+}
+
+#[cw_serde]
+pub struct DrawProofResponse {
+  pub record: Option<DrawRecord>,
+}
+
+#[cw_serde]
+pub struct TicketsResponse {
+  pub wallet: Addr,
+  pub tickets: Vec<TicketRange>,
+}
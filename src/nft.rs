@@ -0,0 +1,34 @@
+use cosmwasm_std::{to_binary, Addr, CosmosMsg, QuerierWrapper, StdResult, WasmMsg};
+use cw721::{Cw721ExecuteMsg, Cw721QueryMsg, OwnerOfResponse};
+
+/// Build a msg that transfers an NFT held by this contract to the recipient.
+pub fn build_cw721_transfer_msg(
+  collection_address: &Addr,
+  token_id: &String,
+  recipient: &Addr,
+) -> StdResult<CosmosMsg> {
+  Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+    contract_addr: collection_address.to_string(),
+    msg: to_binary(&Cw721ExecuteMsg::TransferNft {
+      recipient: recipient.to_string(),
+      token_id: token_id.clone(),
+    })?,
+    funds: vec![],
+  }))
+}
+
+/// Return the current owner of an NFT.
+pub fn query_cw721_owner(
+  querier: &QuerierWrapper,
+  collection_address: &Addr,
+  token_id: &String,
+) -> StdResult<String> {
+  let resp: OwnerOfResponse = querier.query_wasm_smart(
+    collection_address,
+    &Cw721QueryMsg::OwnerOf {
+      token_id: token_id.clone(),
+      include_expired: None,
+    },
+  )?;
+  Ok(resp.owner)
+}
@@ -0,0 +1,210 @@
+use cosmwasm_std::{Addr, Deps, Env, Uint128};
+use cw_lib::utils::funds::get_token_balance;
+
+use crate::{
+  models::{ContractResult, FeeConfig, Raffle, RoyaltyRecipient},
+  state::{fee_config, ROYALTIES},
+};
+
+/// Amount of the ticket price token held by the contract, excluding prizes
+/// paid in the same token and unclaimed referral earnings. This is what gets
+/// split between recipients.
+pub fn get_proceeds(
+  deps: Deps,
+  env: &Env,
+  raffle: &Raffle,
+) -> ContractResult<Uint128> {
+  let balance = get_token_balance(deps.querier, &env.contract.address, &raffle.price.token)?;
+  let prize_reserve = raffle.get_funded_amount(&raffle.price.token);
+  Ok(
+    balance
+      .saturating_sub(prize_reserve)
+      .saturating_sub(raffle.referral_earnings),
+  )
+}
+
+/// Load royalties and fees from state and split the raffle's proceeds.
+pub fn load_payouts(
+  deps: Deps,
+  env: &Env,
+  raffle: &Raffle,
+) -> ContractResult<Vec<(Addr, Uint128)>> {
+  let proceeds = get_proceeds(deps, env, raffle)?;
+  let royalties: Vec<RoyaltyRecipient> = ROYALTIES
+    .iter(deps.storage)?
+    .filter_map(|x| x.ok())
+    .collect();
+  let fees = fee_config(deps.storage)?;
+  Ok(calculate_payouts(proceeds, &royalties, &fees))
+}
+
+/// Split proceeds into (address, amount) payouts, one per distinct address.
+/// Fee recipients take their basis points of the proceeds and royalty
+/// recipients split the rest by percentage. Rounding dust goes to the fee
+/// config's remainder address, or else to the first royalty recipient, so
+/// that the full amount is always paid out.
+pub fn calculate_payouts(
+  proceeds: Uint128,
+  royalties: &[RoyaltyRecipient],
+  fees: &FeeConfig,
+) -> Vec<(Addr, Uint128)> {
+  let mut payouts: Vec<(Addr, Uint128)> = vec![];
+
+  let mut total_fees = Uint128::zero();
+  for recipient in fees.recipients.iter() {
+    let amount = proceeds.multiply_ratio(recipient.bps, 10_000u128);
+    total_fees += amount;
+    add_payout(&mut payouts, &recipient.address, amount);
+  }
+
+  let total_royalties = proceeds - total_fees;
+  for recipient in royalties.iter() {
+    let amount = total_royalties.multiply_ratio(recipient.pct, 100u128);
+    add_payout(&mut payouts, &recipient.address, amount);
+  }
+
+  let total_paid: Uint128 = payouts.iter().map(|(_, amount)| *amount).sum();
+  let remainder_addr = fees
+    .remainder_address
+    .as_ref()
+    .or(royalties.first().map(|r| &r.address))
+    .or(fees.recipients.first().map(|r| &r.address))
+    .cloned();
+
+  if let Some(addr) = remainder_addr {
+    add_payout(&mut payouts, &addr, proceeds - total_paid);
+  }
+
+  payouts.retain(|(_, amount)| !amount.is_zero());
+  payouts
+}
+
+fn add_payout(
+  payouts: &mut Vec<(Addr, Uint128)>,
+  addr: &Addr,
+  amount: Uint128,
+) {
+  if let Some(payout) = payouts.iter_mut().find(|(a, _)| a == addr) {
+    payout.1 += amount;
+  } else {
+    payouts.push((addr.clone(), amount));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::FeeRecipient;
+
+  fn fee_config(
+    recipients: &[(&str, u16)],
+    remainder_address: Option<&str>,
+  ) -> FeeConfig {
+    FeeConfig {
+      recipients: recipients
+        .iter()
+        .map(|(address, bps)| FeeRecipient {
+          name: None,
+          address: Addr::unchecked(*address),
+          bps: *bps,
+        })
+        .collect(),
+      house_revenue: vec![],
+      remainder_address: remainder_address.map(Addr::unchecked),
+      referral_bps: 0,
+    }
+  }
+
+  fn royalties(recipients: &[(&str, u8)]) -> Vec<RoyaltyRecipient> {
+    recipients
+      .iter()
+      .map(|(address, pct)| RoyaltyRecipient {
+        name: None,
+        address: Addr::unchecked(*address),
+        pct: *pct,
+      })
+      .collect()
+  }
+
+  fn payouts_of(payouts: &[(Addr, Uint128)]) -> Vec<(&str, u128)> {
+    payouts
+      .iter()
+      .map(|(addr, amount)| (addr.as_str(), amount.u128()))
+      .collect()
+  }
+
+  fn total(payouts: &[(Addr, Uint128)]) -> Uint128 {
+    payouts.iter().map(|(_, amount)| *amount).sum()
+  }
+
+  #[test]
+  fn dust_goes_to_first_royalty_recipient() {
+    let proceeds = Uint128::from(1_000_003u128);
+    let payouts = calculate_payouts(
+      proceeds,
+      &royalties(&[("c", 33), ("d", 33), ("e", 34)]),
+      &fee_config(&[("a", 250), ("b", 125)], None),
+    );
+    assert_eq!(
+      payouts_of(&payouts),
+      vec![
+        ("a", 25_000),
+        ("b", 12_500),
+        ("c", 317_627),
+        ("d", 317_625),
+        ("e", 327_251),
+      ]
+    );
+    assert_eq!(total(&payouts), proceeds);
+  }
+
+  #[test]
+  fn recipient_in_both_lists_gets_one_payout() {
+    let proceeds = Uint128::from(1_001u128);
+    let payouts = calculate_payouts(
+      proceeds,
+      &royalties(&[("x", 60), ("y", 40)]),
+      &fee_config(&[("x", 500)], None),
+    );
+    assert_eq!(payouts_of(&payouts), vec![("x", 621), ("y", 380)]);
+    assert_eq!(total(&payouts), proceeds);
+  }
+
+  #[test]
+  fn dust_falls_back_to_first_fee_recipient() {
+    let proceeds = Uint128::from(10u128);
+    let payouts = calculate_payouts(
+      proceeds,
+      &[],
+      &fee_config(&[("a", 3_333), ("b", 3_333)], None),
+    );
+    assert_eq!(payouts_of(&payouts), vec![("a", 7), ("b", 3)]);
+    assert_eq!(total(&payouts), proceeds);
+  }
+
+  #[test]
+  fn dust_goes_to_remainder_address() {
+    let proceeds = Uint128::from(1_000_003u128);
+    let payouts = calculate_payouts(
+      proceeds,
+      &royalties(&[("c", 33), ("d", 67)]),
+      &fee_config(&[("a", 250)], Some("r")),
+    );
+    // c gets 33% of 975_003 and d 67%, each rounded down
+    assert_eq!(
+      payouts_of(&payouts),
+      vec![("a", 25_000), ("c", 321_750), ("d", 653_252), ("r", 1)]
+    );
+    assert_eq!(total(&payouts), proceeds);
+  }
+
+  #[test]
+  fn nothing_to_pay_out() {
+    let payouts = calculate_payouts(
+      Uint128::zero(),
+      &royalties(&[("c", 100)]),
+      &fee_config(&[("a", 250)], None),
+    );
+    assert!(payouts.is_empty());
+  }
+}
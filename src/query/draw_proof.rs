@@ -0,0 +1,8 @@
+use crate::{models::ContractResult, msg::DrawProofResponse, state::DRAW_RECORD};
+use cosmwasm_std::Deps;
+
+pub fn draw_proof(deps: Deps) -> ContractResult<DrawProofResponse> {
+  Ok(DrawProofResponse {
+    record: DRAW_RECORD.may_load(deps.storage)?,
+  })
+}
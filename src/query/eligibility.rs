@@ -0,0 +1,16 @@
+use crate::{
+  gate::check_eligibility, models::ContractResult, msg::EligibilityResponse, state::RAFFLE,
+};
+use cosmwasm_std::{Addr, Deps};
+
+pub fn eligibility(
+  deps: Deps,
+  wallet: &Addr,
+) -> ContractResult<EligibilityResponse> {
+  let raffle = RAFFLE.load(deps.storage)?;
+  let reason = check_eligibility(deps, &raffle, wallet, None)?;
+  Ok(EligibilityResponse {
+    is_eligible: reason.is_none(),
+    reason,
+  })
+}
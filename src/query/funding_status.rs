@@ -0,0 +1,32 @@
+use crate::{
+  models::{ContractResult, RaffleAsset},
+  msg::{AssetFundingStatus, FundingStatusResponse},
+  state::RAFFLE,
+};
+use cosmwasm_std::Deps;
+
+pub fn funding_status(deps: Deps) -> ContractResult<FundingStatusResponse> {
+  let raffle = RAFFLE.load(deps.storage)?;
+  Ok(FundingStatusResponse {
+    is_funded: raffle.is_funded(),
+    assets: raffle
+      .assets
+      .iter()
+      .enumerate()
+      .filter_map(|(i, asset)| match asset {
+        RaffleAsset::Token {
+          token,
+          amount,
+          funded_amount,
+          ..
+        } => Some(AssetFundingStatus {
+          index: i as u32,
+          token: token.clone(),
+          amount: *amount,
+          funded_amount: *funded_amount,
+        }),
+        _ => None,
+      })
+      .collect(),
+  })
+}
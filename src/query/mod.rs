@@ -1,7 +1,21 @@
+mod draw_proof;
+mod eligibility;
+mod funding_status;
+mod payout_preview;
+mod quote_price;
+mod referrals;
 mod refund_status;
 mod select;
 mod test_random;
+mod tickets;
 
+pub use draw_proof::draw_proof;
+pub use eligibility::eligibility;
+pub use funding_status::funding_status;
+pub use payout_preview::payout_preview;
+pub use quote_price::quote_price;
+pub use referrals::referrals;
 pub use refund_status::refund_status;
 pub use select::select;
 pub use test_random::test_random;
+pub use tickets::tickets;
@@ -0,0 +1,23 @@
+use crate::{
+  models::ContractResult,
+  msg::{Payout, PayoutPreviewResponse},
+  payout::load_payouts,
+  state::RAFFLE,
+};
+use cosmwasm_std::{Deps, Env, Uint128};
+
+pub fn payout_preview(
+  deps: Deps,
+  env: &Env,
+) -> ContractResult<PayoutPreviewResponse> {
+  let raffle = RAFFLE.load(deps.storage)?;
+  let payouts: Vec<Payout> = load_payouts(deps, env, &raffle)?
+    .into_iter()
+    .map(|(address, amount)| Payout { address, amount })
+    .collect();
+  Ok(PayoutPreviewResponse {
+    token: raffle.price.token.clone(),
+    total: payouts.iter().map(|p| p.amount).sum::<Uint128>(),
+    payouts,
+  })
+}
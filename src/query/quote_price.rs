@@ -0,0 +1,27 @@
+use crate::{
+  models::ContractResult,
+  msg::QuotePriceResponse,
+  state::{RAFFLE, WALLET_METADATA},
+};
+use cosmwasm_std::{Addr, Deps};
+
+pub fn quote_price(
+  deps: Deps,
+  count: u32,
+  wallet: Option<Addr>,
+) -> ContractResult<QuotePriceResponse> {
+  let raffle = RAFFLE.load(deps.storage)?;
+  let wallet_ticket_count = match wallet {
+    Some(addr) => WALLET_METADATA
+      .may_load(deps.storage, addr)?
+      .map(|meta| meta.ticket_count)
+      .unwrap_or(0),
+    None => 0,
+  };
+  Ok(QuotePriceResponse {
+    token: raffle.price.token.clone(),
+    amount: raffle.get_order_price(count),
+    discount_bps: raffle.get_discount_bps(count),
+    ticket_allowance: raffle.get_ticket_allowance(wallet_ticket_count),
+  })
+}
@@ -0,0 +1,28 @@
+use crate::{models::ContractResult, msg::ReferralsResponse, state::REFERRAL_EARNINGS};
+use cosmwasm_std::{Addr, Deps, Uint128};
+
+pub fn referrals(
+  deps: Deps,
+  referrer: &Addr,
+) -> ContractResult<ReferralsResponse> {
+  Ok(
+    match REFERRAL_EARNINGS.may_load(deps.storage, referrer.clone())? {
+      Some(referral) => ReferralsResponse {
+        referrer: referrer.clone(),
+        volume: referral.volume,
+        order_count: referral.order_count,
+        ticket_count: referral.ticket_count,
+        earnings: referral.earnings,
+        has_claimed: referral.has_claimed,
+      },
+      None => ReferralsResponse {
+        referrer: referrer.clone(),
+        volume: Uint128::zero(),
+        order_count: 0,
+        ticket_count: 0,
+        earnings: Uint128::zero(),
+        has_claimed: false,
+      },
+    },
+  )
+}
@@ -2,7 +2,8 @@ use crate::{
   models::{ContractResult, WalletMetadata},
   msg::SelectResponse,
   state::{
-    ACL_ADDRESS, MARKETING_INFO, RAFFLE, RAFFLE_OWNER, ROYALTIES, TICKET_ORDERS, WALLET_METADATA,
+    load_ticket_ranges, ACL_ADDRESS, FEE_CONFIG, MARKETING_INFO, RAFFLE, RAFFLE_OWNER, ROYALTIES,
+    TICKET_ORDERS, WALLET_METADATA,
   },
 };
 use cosmwasm_std::{Addr, Deps, Order};
@@ -23,6 +24,8 @@ pub fn select(
 
     marketing: loader.get("marketing", &MARKETING_INFO)?,
 
+    fees: loader.get("fees", &FEE_CONFIG)?,
+
     wallets: loader.view("wallets", |_| {
       let mut wallet_metas: Vec<WalletMetadata> = WALLET_METADATA
         .range(deps.storage, None, None, Order::Descending)
@@ -43,6 +46,27 @@ pub fn select(
       ))
     })?,
 
+    ticket_allowance: loader.view("ticket_allowance", |_| {
+      if let Some(wallet) = &wallet {
+        let raffle = RAFFLE.load(deps.storage)?;
+        let wallet_ticket_count = WALLET_METADATA
+          .may_load(deps.storage, wallet.clone())?
+          .map(|meta| meta.ticket_count)
+          .unwrap_or(0);
+        Ok(raffle.get_ticket_allowance(wallet_ticket_count))
+      } else {
+        Ok(None)
+      }
+    })?,
+
+    tickets: loader.view("tickets", |_| {
+      if let Some(wallet) = &wallet {
+        Ok(Some(load_ticket_ranges(deps.storage, wallet)?))
+      } else {
+        Ok(None)
+      }
+    })?,
+
     orders: loader.view("orders", |_| {
       Ok(Some(
         TICKET_ORDERS
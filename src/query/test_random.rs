@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use crate::{
-  models::ContractResult, msg::RandomResponse, selection::resolve_multiple_winners, state::RAFFLE,
+  models::ContractResult, msg::RandomResponse, selection::simulate_winners, state::RAFFLE,
 };
 use cosmwasm_std::{Addr, Deps, Env};
 
@@ -16,7 +16,7 @@ pub fn test_random(
     return Ok(RandomResponse { results: vec![] });
   }
 
-  for addr in resolve_multiple_winners(deps.storage, &raffle, env, 100)? {
+  for addr in simulate_winners(deps.storage, &raffle, env, 100)? {
     results_map.insert(addr.clone(), *(results_map.get(&addr).unwrap_or(&0)) + 1);
   }
   Ok(RandomResponse {
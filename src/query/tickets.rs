@@ -0,0 +1,12 @@
+use crate::{models::ContractResult, msg::TicketsResponse, state::load_ticket_ranges};
+use cosmwasm_std::{Addr, Deps};
+
+pub fn tickets(
+  deps: Deps,
+  wallet: &Addr,
+) -> ContractResult<TicketsResponse> {
+  Ok(TicketsResponse {
+    wallet: wallet.clone(),
+    tickets: load_ticket_ranges(deps.storage, wallet)?,
+  })
+}
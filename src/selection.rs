@@ -1,73 +1,412 @@
-use std::collections::HashMap;
-
-use cosmwasm_std::{Addr, Env, Order, Storage};
+use cosmwasm_std::{Addr, Env, HexBinary, Order, StdError, StdResult, Storage};
 use cw_lib::random::{Pcg64, RngComponent};
+use cw_storage_plus::Bound;
 
 use crate::{
-  models::{ContractResult, Raffle},
-  state::WALLET_METADATA,
+  models::{ContractResult, DrawRecord, DrawnTicket, Raffle, SelectionMode},
+  state::{TICKET_OWNERS, WALLET_TICKETS},
 };
 
-pub fn draw_winner(
+/// Describes how tickets are numbered and drawn, for reproducing draws
+/// off-chain.
+pub const TICKET_ORDERING: &str = "tickets numbered from 0 in purchase order; \
+  ticket_index counts only tickets still in the draw, skipping tickets already \
+  drawn in ticket selection mode or held by earlier winners in wallet selection mode";
+
+/// Draw one distinct winner per prize rank, in rank order, and record every
+/// input to the draw so that it can be reproduced off-chain. If there are fewer
+/// wallets than ranks, the trailing ranks go without a winner.
+pub fn draw_winners(
   storage: &dyn Storage,
   raffle: &Raffle,
   env: &Env,
-) -> ContractResult<Addr> {
-  let addrs = resolve_multiple_winners(storage, raffle, env, 1)?;
-  Ok(addrs[0].clone())
+  entropy: Option<&HexBinary>,
+) -> ContractResult<DrawRecord> {
+  let count = raffle.rank_count() as u32;
+  Ok(DrawRecord {
+    seed: raffle.seed.clone(),
+    block_time: env.block.time,
+    block_height: env.block.height,
+    tx_index: get_tx_index(env),
+    entropy: entropy.cloned(),
+    ordering: TICKET_ORDERING.to_string(),
+    selection_mode: raffle.selection_mode.clone(),
+    total_tickets: raffle.tickets_sold,
+    draws: resolve_multiple_winners(storage, raffle, env, count, entropy)?,
+  })
 }
 
+/// Draw `count` winners, excluding tickets between draws according to the
+/// raffle's selection mode. `entropy` is the beacon's randomness or the
+/// owner's revealed secret, if the raffle uses either.
 pub fn resolve_multiple_winners(
   storage: &dyn Storage,
   raffle: &Raffle,
   env: &Env,
   count: u32,
+  entropy: Option<&HexBinary>,
+) -> ContractResult<Vec<DrawnTicket>> {
+  let mut rng = build_rng(raffle, env, entropy);
+  draw_tickets(storage, &mut rng, raffle, count)
+}
+
+/// Run the multi-winner draw `trials` times, each time with every ticket back
+/// in the draw, returning every winner drawn across all trials.
+pub fn simulate_winners(
+  storage: &dyn Storage,
+  raffle: &Raffle,
+  env: &Env,
+  trials: u32,
 ) -> ContractResult<Vec<Addr>> {
-  let mut rng = Pcg64::from_components(&vec![
+  let mut rng = build_rng(raffle, env, None);
+  let count = raffle.rank_count() as u32;
+  let mut addrs: Vec<Addr> = Vec::with_capacity((trials * count) as usize);
+  for _ in 0..trials {
+    for drawn in draw_tickets(storage, &mut rng, raffle, count)? {
+      addrs.push(drawn.winner);
+    }
+  }
+  Ok(addrs)
+}
+
+/// Build the draw's RNG. When external entropy is given, it replaces the block
+/// data that validators, buyers and the owner could otherwise influence. In
+/// particular, a revealed secret fixes the outcome once ticket sales close, so
+/// the owner can't pick a block in which to reveal it that favors a winner.
+fn build_rng(
+  raffle: &Raffle,
+  env: &Env,
+  entropy: Option<&HexBinary>,
+) -> Pcg64 {
+  if let Some(entropy) = entropy {
+    return Pcg64::from_components(&vec![
+      RngComponent::Str(raffle.seed.clone()),
+      RngComponent::Str(entropy.to_hex()),
+    ]);
+  }
+  Pcg64::from_components(&vec![
     RngComponent::Str(raffle.seed.clone()),
     RngComponent::Int(env.block.time.nanos()),
     RngComponent::Int(env.block.height),
-    RngComponent::Int(
-      env
-        .transaction
-        .as_ref()
-        .and_then(|x| Some(x.index as u64))
-        .or(Some(0))
-        .unwrap(),
-    ),
-  ]);
-
-  let mut bag: Vec<usize> = Vec::with_capacity(raffle.tickets_sold as usize);
-  let mut idx: usize = 0;
-  let mut idx_2_addr: HashMap<usize, Addr> = HashMap::new();
-  let mut addr_2_idx: HashMap<Addr, usize> = HashMap::new();
-
-  WALLET_METADATA
-    .range(storage, None, None, Order::Ascending)
-    .for_each(|result| {
-      if let Ok((addr, meta)) = result {
-        let idx = match addr_2_idx.get(&addr) {
-          Some(idx) => *idx,
-          None => {
-            idx_2_addr.insert(idx, addr.clone());
-            addr_2_idx.insert(addr, idx);
-            idx += 1;
-            idx - 1
-          },
-        };
-        for _ in 0..meta.ticket_count {
-          bag.push(idx);
+    RngComponent::Int(get_tx_index(env)),
+  ])
+}
+
+fn get_tx_index(env: &Env) -> u64 {
+  env
+    .transaction
+    .as_ref()
+    .and_then(|x| Some(x.index as u64))
+    .or(Some(0))
+    .unwrap()
+}
+
+/// Draw up to `count` tickets, each uniformly from the tickets still in the
+/// draw. Rather than building a bag with one entry per ticket, a random index
+/// into the remaining tickets is mapped to a ticket number by skipping over
+/// excluded ticket ranges, and the ticket's owner is found by looking up the
+/// order containing it. This keeps the cost of a draw independent of the
+/// number of tickets sold.
+fn draw_tickets(
+  storage: &dyn Storage,
+  rng: &mut Pcg64,
+  raffle: &Raffle,
+  count: u32,
+) -> ContractResult<Vec<DrawnTicket>> {
+  let mut drawn: Vec<DrawnTicket> = Vec::with_capacity(count as usize);
+  // (first ticket, ticket count) ranges no longer in the draw, sorted
+  let mut excluded: Vec<(u32, u32)> = vec![];
+  let mut tickets_remaining = raffle.tickets_sold;
+
+  for rank in 1..=count {
+    if tickets_remaining == 0 {
+      break;
+    }
+    let random_value = rng.next_u64();
+    let tickets_in_draw = tickets_remaining;
+    let ticket_index = (random_value % (tickets_in_draw as u64)) as u32;
+
+    let ticket_number = skip_excluded(&excluded, ticket_index);
+    let winner = get_ticket_owner(storage, ticket_number)?;
+
+    match raffle.selection_mode {
+      SelectionMode::Wallet => {
+        for result in
+          WALLET_TICKETS
+            .prefix(winner.clone())
+            .range(storage, None, None, Order::Ascending)
+        {
+          let (first, n) = result?;
+          excluded.push((first, n));
+          tickets_remaining -= n;
         }
-      }
+      },
+      SelectionMode::Ticket => {
+        excluded.push((ticket_number, 1));
+        tickets_remaining -= 1;
+      },
+    }
+    excluded.sort_unstable();
+
+    drawn.push(DrawnTicket {
+      rank: rank as u8,
+      random_value,
+      tickets_remaining: tickets_in_draw,
+      ticket_index,
+      ticket_number,
+      winner,
     });
+  }
 
-  let mut addrs: Vec<Addr> = Vec::with_capacity(count as usize);
+  Ok(drawn)
+}
 
-  for _ in 0..(count as usize) {
-    let bag_index = rng.next_u64() % (bag.len() as u64);
-    let addr_index = bag[bag_index as usize].clone();
-    addrs.push(idx_2_addr.get(&addr_index).unwrap().clone())
+/// Map an index into the tickets still in the draw to its ticket number, by
+/// skipping over the sorted, non-overlapping excluded (first ticket, count)
+/// ranges at or before it.
+fn skip_excluded(
+  excluded: &[(u32, u32)],
+  ticket_index: u32,
+) -> u32 {
+  let mut ticket_number = ticket_index;
+  for (first, n) in excluded.iter() {
+    if *first > ticket_number {
+      break;
+    }
+    ticket_number += n;
   }
+  ticket_number
+}
 
-  Ok(addrs)
+/// Return the owner of the given ticket, from the order whose range of ticket
+/// numbers contains it.
+pub fn get_ticket_owner(
+  storage: &dyn Storage,
+  ticket_number: u32,
+) -> StdResult<Addr> {
+  TICKET_OWNERS
+    .range(
+      storage,
+      None,
+      Some(Bound::inclusive(ticket_number)),
+      Order::Descending,
+    )
+    .next()
+    .unwrap_or_else(|| Err(StdError::not_found("ticket owner")))
+    .map(|(_, addr)| addr)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    models::{RaffleStatus, RandomnessSource},
+    state::index_tickets,
+  };
+  use cosmwasm_std::{testing::mock_env, testing::MockStorage, Uint128};
+  use cw_lib::models::{Token, TokenAmount};
+  use std::collections::HashSet;
+
+  const WALLET_COUNT: u32 = 250;
+  const ORDER_COUNT: u32 = 4_000;
+
+  /// Index orders of 1 to 500 tickets, spread over many wallets, for about
+  /// 1M tickets in total. Returns each order's (owner, first ticket, count).
+  fn index_orders(storage: &mut MockStorage) -> Vec<(Addr, u32, u32)> {
+    let mut orders = Vec::with_capacity(ORDER_COUNT as usize);
+    let mut next_ticket = 0;
+    for i in 0..ORDER_COUNT {
+      let owner = Addr::unchecked(format!("wallet{}", (i * 7) % WALLET_COUNT));
+      let count = 1 + (i * 7_919) % 500;
+      index_tickets(storage, &owner, next_ticket, count).unwrap();
+      orders.push((owner, next_ticket, count));
+      next_ticket += count;
+    }
+    orders
+  }
+
+  fn build_raffle(
+    tickets_sold: u32,
+    selection_mode: SelectionMode,
+  ) -> Raffle {
+    Raffle {
+      price: TokenAmount {
+        token: Token::Native {
+          denom: "ujuno".to_string(),
+        },
+        amount: Uint128::one(),
+      },
+      assets: vec![],
+      status: RaffleStatus::Active,
+      ticket_supply: None,
+      ticket_sales_start_at: None,
+      ticket_sales_end_at: None,
+      ticket_sales_target: None,
+      max_tickets_per_wallet: None,
+      max_tickets_per_order: None,
+      sale_gate: None,
+      merkle_root: None,
+      price_tiers: None,
+      canceled_at: None,
+      winner_address: None,
+      winners: vec![],
+      selection_mode,
+      randomness_source: RandomnessSource::Block,
+      draw_requested_at: None,
+      referral_earnings: Uint128::zero(),
+      refunds_outstanding: Uint128::zero(),
+      tickets_sold,
+      free_tickets: 0,
+      wallet_count: WALLET_COUNT,
+      seed: "seed".to_string(),
+    }
+  }
+
+  fn total_tickets(orders: &[(Addr, u32, u32)]) -> u32 {
+    orders.iter().map(|(_, _, count)| count).sum()
+  }
+
+  #[test]
+  fn ticket_owner_at_order_boundaries() {
+    let mut storage = MockStorage::new();
+    let orders = index_orders(&mut storage);
+    assert!(total_tickets(&orders) > 990_000);
+
+    for (owner, first, count) in orders.iter() {
+      let last = first + count - 1;
+      assert_eq!(get_ticket_owner(&storage, *first).unwrap(), *owner);
+      assert_eq!(get_ticket_owner(&storage, last).unwrap(), *owner);
+      assert_eq!(
+        get_ticket_owner(&storage, first + count / 2).unwrap(),
+        *owner
+      );
+    }
+  }
+
+  #[test]
+  fn skip_never_lands_on_excluded_ticket() {
+    // ranges at the start, adjacent to each other and at the end
+    let total = 1_000u32;
+    let excluded = vec![(0, 3), (10, 1), (11, 4), (500, 250), (990, 10)];
+    let excluded_count: u32 = excluded.iter().map(|(_, n)| n).sum();
+    let is_excluded = |t: u32| {
+      excluded
+        .iter()
+        .any(|(first, n)| t >= *first && t < first + n)
+    };
+
+    // every index maps to a distinct ticket still in the draw, in order, so
+    // the mapping covers every such ticket exactly once
+    let mut previous: Option<u32> = None;
+    for ticket_index in 0..(total - excluded_count) {
+      let ticket_number = skip_excluded(&excluded, ticket_index);
+      assert!(ticket_number < total);
+      assert!(!is_excluded(ticket_number));
+      assert!(previous.map(|p| ticket_number > p).unwrap_or(true));
+      previous = Some(ticket_number);
+    }
+    let remaining = (0..total).filter(|t| !is_excluded(*t)).count() as u32;
+    assert_eq!(remaining, total - excluded_count);
+  }
+
+  #[test]
+  fn skip_excludes_winning_wallets_tickets() {
+    let mut storage = MockStorage::new();
+    let orders = index_orders(&mut storage);
+    let total = total_tickets(&orders);
+
+    // exclude every ticket held by the first few wallets
+    let winners: Vec<Addr> = (0..5)
+      .map(|i| Addr::unchecked(format!("wallet{}", i * 7)))
+      .collect();
+    let mut excluded: Vec<(u32, u32)> = orders
+      .iter()
+      .filter(|(owner, _, _)| winners.contains(owner))
+      .map(|(_, first, count)| (*first, *count))
+      .collect();
+    excluded.sort_unstable();
+    let remaining = total - excluded.iter().map(|(_, n)| n).sum::<u32>();
+
+    for ticket_index in (0..remaining).step_by(997).chain(vec![0, remaining - 1]) {
+      let ticket_number = skip_excluded(&excluded, ticket_index);
+      assert!(ticket_number < total);
+      let owner = get_ticket_owner(&storage, ticket_number).unwrap();
+      assert!(!winners.contains(&owner));
+    }
+  }
+
+  #[test]
+  fn wallet_mode_draws_distinct_wallets() {
+    let mut storage = MockStorage::new();
+    let orders = index_orders(&mut storage);
+    let total = total_tickets(&orders);
+    let raffle = build_raffle(total, SelectionMode::Wallet);
+    let mut rng = build_rng(&raffle, &mock_env(), None);
+
+    let drawn = draw_tickets(&storage, &mut rng, &raffle, 20).unwrap();
+    assert_eq!(drawn.len(), 20);
+
+    let mut winners: HashSet<Addr> = HashSet::new();
+    let mut tickets_remaining = total;
+    for ticket in drawn.iter() {
+      assert_eq!(ticket.tickets_remaining, tickets_remaining);
+      assert!(ticket.ticket_index < tickets_remaining);
+      assert!(ticket.ticket_number < total);
+      assert_eq!(
+        get_ticket_owner(&storage, ticket.ticket_number).unwrap(),
+        ticket.winner
+      );
+      assert!(winners.insert(ticket.winner.clone()));
+      let wallet_tickets: u32 = orders
+        .iter()
+        .filter(|(owner, _, _)| *owner == ticket.winner)
+        .map(|(_, _, count)| count)
+        .sum();
+      tickets_remaining -= wallet_tickets;
+    }
+  }
+
+  #[test]
+  fn ticket_mode_draws_distinct_tickets() {
+    let mut storage = MockStorage::new();
+    let orders = index_orders(&mut storage);
+    let total = total_tickets(&orders);
+    let raffle = build_raffle(total, SelectionMode::Ticket);
+    let mut rng = build_rng(&raffle, &mock_env(), None);
+
+    let drawn = draw_tickets(&storage, &mut rng, &raffle, 100).unwrap();
+    assert_eq!(drawn.len(), 100);
+
+    let mut ticket_numbers: HashSet<u32> = HashSet::new();
+    for (i, ticket) in drawn.iter().enumerate() {
+      assert_eq!(ticket.tickets_remaining, total - i as u32);
+      assert!(ticket.ticket_number < total);
+      assert_eq!(
+        get_ticket_owner(&storage, ticket.ticket_number).unwrap(),
+        ticket.winner
+      );
+      assert!(ticket_numbers.insert(ticket.ticket_number));
+    }
+  }
+
+  #[test]
+  fn draw_ends_when_tickets_run_out() {
+    let mut storage = MockStorage::new();
+    index_tickets(&mut storage, &Addr::unchecked("wallet0"), 0, 3).unwrap();
+    index_tickets(&mut storage, &Addr::unchecked("wallet1"), 3, 2).unwrap();
+
+    let raffle = build_raffle(5, SelectionMode::Wallet);
+    let mut rng = build_rng(&raffle, &mock_env(), None);
+    assert_eq!(
+      draw_tickets(&storage, &mut rng, &raffle, 3).unwrap().len(),
+      2
+    );
+
+    let raffle = build_raffle(5, SelectionMode::Ticket);
+    let mut rng = build_rng(&raffle, &mock_env(), None);
+    let drawn = draw_tickets(&storage, &mut rng, &raffle, 6).unwrap();
+    let mut ticket_numbers: Vec<u32> = drawn.iter().map(|t| t.ticket_number).collect();
+    ticket_numbers.sort_unstable();
+    assert_eq!(ticket_numbers, vec![0, 1, 2, 3, 4]);
+  }
 }
@@ -1,15 +1,56 @@
+use std::collections::HashSet;
+
 use crate::models::{
-  ContractResult, Raffle, RaffleMarketingInfo, RaffleStatus, RoyaltyRecipient, WalletMetadata,
+  ContractResult, DrawRecord, FeeConfig, FeeRecipient, HouseRevenue, Raffle, RaffleAsset,
+  RaffleMarketingInfo, RaffleStatus, RandomnessSource, ReferralEarnings, RoyaltyRecipient,
+  SaleGate, TicketRange, WalletMetadata,
 };
 use crate::msg::InstantiateMsg;
+use crate::nft::query_cw721_owner;
 use crate::{error::ContractError, models::TicketOrder};
-use cosmwasm_std::{Addr, Binary, Deps, DepsMut, Env, MessageInfo, StdResult, Storage};
+use cosmwasm_std::{
+  Addr, Api, Binary, Deps, DepsMut, Env, MessageInfo, Order, StdResult, Storage, Uint128,
+};
 use cw_acl::client::Acl;
+use cw_lib::models::Token;
 use cw_lib::random::{Pcg64, RngComponent};
+use cw_lib::utils::funds::get_token_balance;
 use cw_repository::client::Repository;
 use cw_storage_plus::{Deque, Item, Map};
 
-pub const HOUSE_ADDR: &str = "juno14grw0ge33v2f08yapt5608vhm3d89f5xwmvvw8xeazyvwapm9v6sq86th5";
+/// Time after cancelation when stranded funds may be swept even if some
+/// wallets haven't claimed their refunds yet.
+pub const REFUND_GRACE_PERIOD_SECONDS: u64 = 60 * 60 * 24 * 30;
+
+/// Time after requesting beacon randomness when a raffle still waiting on the
+/// proxy's callback may be canceled.
+pub const DRAW_TIMEOUT_SECONDS: u64 = 60 * 60 * 24;
+
+/// Job ID sent with the randomness request and expected in the callback.
+pub const BEACON_JOB_ID: &str = "choose_winner";
+
+/// Fee recipients and house hard-coded in contract versions that predate the
+/// fee config, used to migrate raffles created by those versions.
+const LEGACY_FEE_RECIPIENTS: [(&str, &str); 4] = [
+  ("Gelotto", "juno1jume25ttjlcaqqjzjjqx9humvze3vcc8z87szj"),
+  (
+    "Gelotto NFT 1 Rewards",
+    "juno18fd2xax0uh9dxusg8uae5rkeu8a4sv3gk6zm7h",
+  ),
+  (
+    "Gelotto NFT 2 Rewards",
+    "juno13c97054tjktvzvgqe2xfxj28j6wmhhlz03ut32",
+  ),
+  (
+    "Gelotto Owners",
+    "juno1dunhw3y4m6lu642lk20hfq9q3scr70l2vuyrwj",
+  ),
+];
+const LEGACY_HOUSE_ADDR: &str = "juno14grw0ge33v2f08yapt5608vhm3d89f5xwmvvw8xeazyvwapm9v6sq86th5";
+const LEGACY_GLTO_CW20_ADDR: &str =
+  "juno1j0a9ymgngasfn3l5me8qpd53l5zlm9wurfdk7r65s5mg6tkxal3qpgf5se";
+const LEGACY_HOUSE_REVENUE_BPS: u16 = 500;
+const LEGACY_ROYALTY_BPS: u128 = 9_000;
 
 pub const IX_U64_TICKETS_SOLD: u8 = 0;
 pub const IX_U64_WALLET_COUNT: u8 = 1;
@@ -27,6 +68,15 @@ pub const TICKET_ORDERS: Deque<TicketOrder> = Deque::new("ticket_orders");
 pub const ROYALTIES: Deque<RoyaltyRecipient> = Deque::new("royalties");
 pub const WALLET_METADATA: Map<Addr, WalletMetadata> = Map::new("wallet_metadata");
 pub const REFUND_STATUSES: Map<Addr, bool> = Map::new("refund_statuses");
+pub const FEE_CONFIG: Item<FeeConfig> = Item::new("fee_config");
+pub const ALLOWLIST: Map<Addr, ()> = Map::new("allowlist");
+/// Owner of each ticket order, keyed by the order's first ticket number.
+pub const TICKET_OWNERS: Map<u32, Addr> = Map::new("ticket_owners");
+/// Ticket count of each of a wallet's orders, keyed by the order's first
+/// ticket number.
+pub const WALLET_TICKETS: Map<(Addr, u32), u32> = Map::new("wallet_tickets");
+pub const DRAW_RECORD: Item<DrawRecord> = Item::new("draw_record");
+pub const REFERRAL_EARNINGS: Map<Addr, ReferralEarnings> = Map::new("referral_earnings");
 
 /// Initialize contract state data.
 pub fn initialize(
@@ -48,12 +98,106 @@ pub fn initialize(
       }
     }
   }
+  // ensure ticket limits allow at least 1 ticket to be bought
+  if msg.max_tickets_per_wallet == Some(0) || msg.max_tickets_per_order == Some(0) {
+    return Err(ContractError::ValidationError {
+      reason: Some("ticket limits must be at least 1".into()),
+    });
+  }
+
+  // ensure the presale merkle root is a hex-encoded sha256 hash
+  if let Some(root) = &msg.merkle_root {
+    if hex::decode(root).map(|x| x.len() != 32).unwrap_or(true) {
+      return Err(ContractError::ValidationError {
+        reason: Some("merkle root must be a hex-encoded sha256 hash".into()),
+      });
+    }
+  }
+
+  // ensure the commit-reveal commitment is well-formed and that the owner
+  // has time to reveal the secret after ticket sales close. sales must end at
+  // a fixed time so that the owner can't pick when to close them.
+  if let Some(RandomnessSource::CommitReveal {
+    commitment,
+    reveal_deadline,
+  }) = &msg.randomness_source
+  {
+    if let Some(commitment) = commitment {
+      validate_commitment(commitment)?;
+    }
+    let sales_end_at = msg
+      .ticket_sales_end_at
+      .ok_or(ContractError::ValidationError {
+        reason: Some("commit-reveal raffles must have a ticket sales end time".into()),
+      })?;
+    if *reveal_deadline <= sales_end_at {
+      return Err(ContractError::ValidationError {
+        reason: Some("reveal deadline must be after ticket sales end".into()),
+      });
+    }
+  }
+
+  // ensure price tiers always leave part of the price to pay. free tickets
+  // are only given out by GrantTickets.
+  for tier in msg.price_tiers.iter().flatten() {
+    if tier.min_count == 0 || tier.discount_bps >= 10_000 {
+      return Err(ContractError::ValidationError {
+        reason: Some("invalid price tier".into()),
+      });
+    }
+  }
+
+  // ensure ticket sales open before they close
+  if let (Some(start), Some(end)) = (msg.ticket_sales_start_at, msg.ticket_sales_end_at) {
+    if start >= end {
+      return Err(ContractError::ValidationError {
+        reason: Some("ticket sales must start before they end".into()),
+      });
+    }
+  }
+
+  validate_addresses(deps.api, env, msg)?;
+  validate_royalties(&msg.royalties)?;
+
   // require at least 1 asset being raffled
   if msg.assets.is_empty() {
     return Err(ContractError::ValidationError {
       reason: Some("at least one asset is required".into()),
     });
   }
+  // ensure prize ranks start at 1 and that no rank is skipped
+  if msg.assets.iter().any(|a| a.rank() == 0) {
+    return Err(ContractError::ValidationError {
+      reason: Some("prize ranks start at 1".into()),
+    });
+  }
+  let rank_count = msg.assets.iter().map(|a| a.rank()).max().unwrap_or(1);
+  for rank in 1..=rank_count {
+    if !msg.assets.iter().any(|a| a.rank() == rank) {
+      return Err(ContractError::ValidationError {
+        reason: Some(format!("no asset assigned to prize rank {}", rank)),
+      });
+    }
+  }
+
+  // NFT and token assets only count as escrowed or funded once received by
+  // the contract. Only NFTs on the contract's own chain can be escrowed.
+  let mut assets = msg.assets.clone();
+  for asset in assets.iter_mut() {
+    match asset {
+      RaffleAsset::Nft {
+        network,
+        is_escrowed,
+        requires_escrow,
+        ..
+      } => {
+        *is_escrowed = false;
+        *requires_escrow = network.is_host(&env.contract.address);
+      },
+      RaffleAsset::Token { funded_amount, .. } => *funded_amount = Uint128::zero(),
+      _ => {},
+    }
+  }
 
   REPO_CONTRACT_ADDR.save(deps.storage, &info.sender)?;
 
@@ -67,6 +211,14 @@ pub fn initialize(
     ROYALTIES.push_back(deps.storage, &recipient)?;
   }
 
+  for addr in msg.allowlist.iter().flatten() {
+    ALLOWLIST.save(deps.storage, addr.clone(), &())?;
+  }
+
+  let fee_config = msg.fee_config.clone().unwrap_or_default();
+  validate_fee_config(&fee_config)?;
+  FEE_CONFIG.save(deps.storage, &fee_config)?;
+
   MARKETING_INFO.save(
     deps.storage,
     &RaffleMarketingInfo {
@@ -83,34 +235,431 @@ pub fn initialize(
     },
   )?;
 
-  RAFFLE.save(
-    deps.storage,
-    &Raffle {
-      assets: msg.assets.clone(),
-      price: msg.price.clone(),
-      status: RaffleStatus::Active,
-      ticket_supply: msg.ticket_supply,
-      ticket_sales_end_at: msg.ticket_sales_end_at,
-      ticket_sales_target: msg.ticket_sales_target,
-      tickets_sold: 0,
-      wallet_count: 0,
-      winner_address: None,
-      seed: Binary::from(Pcg64::build_seed(&vec![
-        RngComponent::Str(info.sender.to_string()),
-        RngComponent::Int(env.block.time.nanos()),
-        RngComponent::Int(env.block.height),
-        RngComponent::Int(
-          env
-            .transaction
-            .as_ref()
-            .and_then(|x| Some(x.index as u64))
-            .or(Some(0))
-            .unwrap(),
-        ),
-      ]))
-      .to_base64(),
+  let mut raffle = Raffle {
+    assets,
+    price: msg.price.clone(),
+    status: RaffleStatus::Active,
+    ticket_supply: msg.ticket_supply,
+    ticket_sales_start_at: msg.ticket_sales_start_at,
+    ticket_sales_end_at: msg.ticket_sales_end_at,
+    ticket_sales_target: msg.ticket_sales_target,
+    max_tickets_per_wallet: msg.max_tickets_per_wallet,
+    max_tickets_per_order: msg.max_tickets_per_order,
+    sale_gate: msg.sale_gate.clone(),
+    merkle_root: msg.merkle_root.clone(),
+    price_tiers: msg.price_tiers.clone(),
+    canceled_at: None,
+    tickets_sold: 0,
+    wallet_count: 0,
+    winner_address: None,
+    winners: vec![],
+    selection_mode: msg.selection_mode.clone().unwrap_or_default(),
+    randomness_source: msg.randomness_source.clone().unwrap_or_default(),
+    draw_requested_at: None,
+    referral_earnings: Uint128::zero(),
+    refunds_outstanding: Uint128::zero(),
+    free_tickets: 0,
+    seed: Binary::from(Pcg64::build_seed(&vec![
+      RngComponent::Str(info.sender.to_string()),
+      RngComponent::Int(env.block.time.nanos()),
+      RngComponent::Int(env.block.height),
+      RngComponent::Int(
+        env
+          .transaction
+          .as_ref()
+          .and_then(|x| Some(x.index as u64))
+          .or(Some(0))
+          .unwrap(),
+      ),
+    ]))
+    .to_base64(),
+  };
+
+  // native token prizes may be funded upfront with the instantiate msg
+  for coin in info.funds.iter() {
+    raffle.fund(
+      &Token::Native {
+        denom: coin.denom.clone(),
+      },
+      coin.amount,
+    )?;
+  }
+
+  RAFFLE.save(deps.storage, &raffle)?;
+  Ok(())
+}
+
+/// Backfill state recorded by earlier versions of the contract: prize funding
+/// and escrow, the fee config, and the amount paid and house revenue for
+/// wallets and ticket orders recorded before amounts were tracked, assuming
+/// full price was paid for every ticket.
+pub fn migrate(
+  deps: DepsMut,
+  env: &Env,
+) -> Result<(), ContractError> {
+  let mut raffle = RAFFLE.load(deps.storage)?;
+
+  // raffles instantiated before fees were configurable have no fee config
+  let is_legacy = FEE_CONFIG.may_load(deps.storage)?.is_none();
+  if is_legacy {
+    let owner = RAFFLE_OWNER.load(deps.storage)?;
+    FEE_CONFIG.save(deps.storage, &build_legacy_fee_config(&raffle, &owner))?;
+  }
+
+  let wallets: Vec<(Addr, WalletMetadata)> = WALLET_METADATA
+    .range(deps.storage, None, None, Order::Ascending)
+    .collect::<StdResult<Vec<_>>>()?;
+
+  // house revenue was forwarded but not recorded by earlier versions. it's
+  // backfilled from the fee config's house rule, which for legacy raffles is
+  // the old 5% of GLTO payments, so that refunds exclude it.
+  let house_bps = FEE_CONFIG
+    .load(deps.storage)?
+    .get_house_revenue(&raffle.price.token)
+    .map(|rule| rule.bps)
+    .unwrap_or(0);
+
+  // earlier versions didn't keep running totals of refunds owed and of free
+  // tickets granted
+  raffle.refunds_outstanding = Uint128::zero();
+  raffle.free_tickets = 0;
+  for (addr, mut meta) in wallets.into_iter() {
+    raffle.free_tickets += meta.free_ticket_count;
+    if meta.amount_paid.is_none() || meta.house_revenue.is_none() {
+      let amount_paid = meta.get_amount_paid(raffle.price.amount);
+      meta.amount_paid = Some(amount_paid);
+      meta.house_revenue = meta
+        .house_revenue
+        .or(Some(amount_paid.multiply_ratio(house_bps, 10_000u128)));
+      WALLET_METADATA.save(deps.storage, addr, &meta)?;
+    }
+    if !meta.has_claimed_refund {
+      raffle.refunds_outstanding += meta.get_refund_amount(raffle.price.amount);
+    }
+  }
+
+  // legacy prizes were never funded or escrowed through the contract, so
+  // ticket sales would otherwise stay closed
+  if is_legacy && raffle.status == RaffleStatus::Active {
+    backfill_legacy_assets(deps.as_ref(), env, &mut raffle)?;
+  }
+  RAFFLE.save(deps.storage, &raffle)?;
+
+  // index ticket numbers for orders placed before tickets were numbered
+  let is_indexed = TICKET_OWNERS
+    .keys(deps.storage, None, None, Order::Ascending)
+    .next()
+    .is_some();
+  let mut next_ticket_number: u32 = 0;
+
+  let mut orders: Vec<TicketOrder> = Vec::with_capacity(TICKET_ORDERS.len(deps.storage)? as usize);
+  while let Some(mut order) = TICKET_ORDERS.pop_front(deps.storage)? {
+    if order.amount_paid.is_none() {
+      order.amount_paid = Some(Uint128::from(order.count) * raffle.price.amount);
+    }
+    if order.house_revenue.is_none() {
+      order.house_revenue = order
+        .amount_paid
+        .map(|amount| amount.multiply_ratio(house_bps, 10_000u128));
+    }
+    // orders for zero tickets, which earlier versions let through, hold no
+    // ticket numbers
+    if order.count == 0 {
+      orders.push(order);
+      continue;
+    }
+    if !is_indexed {
+      index_tickets(
+        deps.storage,
+        &order.address,
+        next_ticket_number,
+        order.count,
+      )?;
+    }
+    if order.first_ticket.is_none() {
+      order.first_ticket = Some(next_ticket_number);
+      order.last_ticket = Some(next_ticket_number + order.count - 1);
+    }
+    next_ticket_number += order.count;
+    orders.push(order);
+  }
+  for order in orders.iter() {
+    TICKET_ORDERS.push_back(deps.storage, order)?;
+  }
+
+  Ok(())
+}
+
+/// Reproduce the fee schedule hard-coded in earlier versions of the contract.
+/// Royalty recipients got 90% of the pot and the rest of the contract's balance
+/// was split evenly between the Gelotto wallets and the raffle owner. When
+/// tickets were priced in GLTO, 5% of each payment went to the house.
+fn build_legacy_fee_config(
+  raffle: &Raffle,
+  owner: &Addr,
+) -> FeeConfig {
+  let glto = Token::Cw20 {
+    address: Addr::unchecked(LEGACY_GLTO_CW20_ADDR),
+  };
+  let house_bps = if raffle.price.token == glto {
+    LEGACY_HOUSE_REVENUE_BPS
+  } else {
+    0
+  };
+
+  // fees are now taken from proceeds net of the house's share rather than
+  // from the pot, so the royalties' 90% of the pot is scaled up accordingly
+  let royalty_bps = LEGACY_ROYALTY_BPS * 10_000 / (10_000 - house_bps as u128);
+  let fee_bps = ((10_000 - royalty_bps) / 5) as u16;
+
+  let mut recipients: Vec<FeeRecipient> = LEGACY_FEE_RECIPIENTS
+    .iter()
+    .map(|(name, address)| FeeRecipient {
+      name: Some(name.to_string()),
+      address: Addr::unchecked(*address),
+      bps: fee_bps,
+    })
+    .collect();
+  recipients.push(FeeRecipient {
+    name: None,
+    address: owner.clone(),
+    bps: fee_bps,
+  });
+
+  FeeConfig {
+    recipients,
+    house_revenue: if house_bps > 0 {
+      vec![HouseRevenue {
+        token: glto,
+        house_address: Addr::unchecked(LEGACY_HOUSE_ADDR),
+        bps: house_bps,
+      }]
+    } else {
+      vec![]
     },
-  )?;
+    remainder_address: None,
+    referral_bps: 0,
+  }
+}
+
+/// Mark a legacy raffle's token prizes as funded by as much of them as the
+/// contract holds, and its NFT prizes as escrowed if the contract already owns
+/// them. Underfunded prizes keep ticket sales closed until funded. NFTs not held by the contract
+/// remain exempt from escrow and are delivered off-chain, as before.
+fn backfill_legacy_assets(
+  deps: Deps,
+  env: &Env,
+  raffle: &mut Raffle,
+) -> Result<(), ContractError> {
+  // ticket proceeds still owed to buyers or referrers can't fund prizes
+  let proceeds = raffle.refunds_outstanding + raffle.referral_earnings;
+  let mut available: Vec<(Token, Uint128)> = vec![];
+  for asset in raffle.assets.iter_mut() {
+    match asset {
+      RaffleAsset::Token {
+        token,
+        amount,
+        funded_amount,
+        ..
+      } => {
+        if !available.iter().any(|(t, _)| t == token) {
+          let balance = get_token_balance(deps.querier, &env.contract.address, token)?;
+          let reserved = if *token == raffle.price.token {
+            proceeds
+          } else {
+            Uint128::zero()
+          };
+          available.push((token.clone(), balance.saturating_sub(reserved)));
+        }
+        if let Some((_, remaining)) = available.iter_mut().find(|(t, _)| t == token) {
+          *funded_amount = (*amount).min(*remaining);
+          *remaining -= *funded_amount;
+        }
+      },
+      RaffleAsset::Nft {
+        network,
+        collection_address,
+        token_id,
+        is_escrowed,
+        requires_escrow,
+        ..
+      } => {
+        if network.is_host(&env.contract.address) {
+          let owner = query_cw721_owner(&deps.querier, collection_address, token_id).ok();
+          if owner == Some(env.contract.address.to_string()) {
+            *is_escrowed = true;
+            *requires_escrow = true;
+          }
+        }
+      },
+      _ => {},
+    }
+  }
+  Ok(())
+}
+
+/// Record the owner of a new order's range of ticket numbers, so that the
+/// owner of any ticket can be looked up without loading every order.
+pub fn index_tickets(
+  storage: &mut dyn Storage,
+  owner: &Addr,
+  first_ticket_number: u32,
+  count: u32,
+) -> StdResult<()> {
+  TICKET_OWNERS.save(storage, first_ticket_number, owner)?;
+  WALLET_TICKETS.save(storage, (owner.clone(), first_ticket_number), &count)
+}
+
+/// Load the ranges of ticket numbers held by a wallet, in purchase order.
+pub fn load_ticket_ranges(
+  storage: &dyn Storage,
+  wallet: &Addr,
+) -> StdResult<Vec<TicketRange>> {
+  WALLET_TICKETS
+    .prefix(wallet.clone())
+    .range(storage, None, None, Order::Ascending)
+    .map(|result| {
+      result.map(|(first_ticket, count)| TicketRange {
+        first_ticket,
+        last_ticket: first_ticket + count - 1,
+      })
+    })
+    .collect()
+}
+
+/// Ensure every address in the instantiate msg is a valid bech32 address,
+/// except for NFT collections on other chains.
+fn validate_addresses(
+  api: &dyn Api,
+  env: &Env,
+  msg: &InstantiateMsg,
+) -> Result<(), ContractError> {
+  let mut addrs: Vec<&Addr> = vec![&msg.owner];
+
+  addrs.extend(msg.acl_address.iter());
+  addrs.extend(msg.org_wallet.iter());
+  addrs.extend(msg.royalties.iter().map(|r| &r.address));
+
+  if let Token::Cw20 { address } = &msg.price.token {
+    addrs.push(address);
+  }
+
+  for asset in msg.assets.iter() {
+    match asset {
+      RaffleAsset::Token {
+        token: Token::Cw20 { address },
+        ..
+      } => addrs.push(address),
+      // collections on other chains have addresses with another prefix
+      RaffleAsset::Nft {
+        network,
+        collection_address,
+        ..
+      } if network.is_host(&env.contract.address) => addrs.push(collection_address),
+      RaffleAsset::Asset {
+        address: Some(address),
+        ..
+      } => addrs.push(address),
+      _ => {},
+    }
+  }
+
+  addrs.extend(msg.allowlist.iter().flatten());
+
+  match &msg.sale_gate {
+    Some(SaleGate::Cw721Holder { collection_address }) => addrs.push(collection_address),
+    Some(SaleGate::Cw20Balance { address, .. }) => addrs.push(address),
+    _ => {},
+  }
+
+  if let Some(RandomnessSource::Beacon { proxy_address }) = &msg.randomness_source {
+    addrs.push(proxy_address);
+  }
+
+  if let Some(fee_config) = &msg.fee_config {
+    addrs.extend(fee_config.recipients.iter().map(|r| &r.address));
+    addrs.extend(fee_config.remainder_address.iter());
+    for rule in fee_config.house_revenue.iter() {
+      addrs.push(&rule.house_address);
+      if let Token::Cw20 { address } = &rule.token {
+        addrs.push(address);
+      }
+    }
+  }
+
+  for addr in addrs {
+    if api.addr_validate(addr.as_str()).is_err() {
+      return Err(ContractError::InvalidAddress {
+        address: addr.to_string(),
+      });
+    }
+  }
+  Ok(())
+}
+
+/// Ensure a commit-reveal commitment is a hex-encoded sha256 hash.
+pub fn validate_commitment(commitment: &str) -> Result<(), ContractError> {
+  if hex::decode(commitment)
+    .map(|x| x.len() != 32)
+    .unwrap_or(true)
+  {
+    return Err(ContractError::ValidationError {
+      reason: Some("commitment must be a hex-encoded sha256 hash".into()),
+    });
+  }
+  Ok(())
+}
+
+/// Ensure royalty percentages sum to exactly 100 without duplicate recipients.
+fn validate_royalties(royalties: &[RoyaltyRecipient]) -> Result<(), ContractError> {
+  let mut seen: HashSet<&Addr> = HashSet::with_capacity(royalties.len());
+  for recipient in royalties.iter() {
+    if !seen.insert(&recipient.address) {
+      return Err(ContractError::DuplicateRoyaltyRecipient {
+        address: recipient.address.to_string(),
+      });
+    }
+  }
+  let total: u32 = royalties.iter().map(|r| r.pct as u32).sum();
+  if total != 100 {
+    return Err(ContractError::InvalidRoyaltyTotal { total });
+  }
+  Ok(())
+}
+
+/// Ensure fees can't exceed the pot and that no recipient is listed twice.
+fn validate_fee_config(fee_config: &FeeConfig) -> Result<(), ContractError> {
+  let mut seen: HashSet<&Addr> = HashSet::with_capacity(fee_config.recipients.len());
+  for recipient in fee_config.recipients.iter() {
+    if !seen.insert(&recipient.address) {
+      return Err(ContractError::DuplicateFeeRecipient {
+        address: recipient.address.to_string(),
+      });
+    }
+  }
+  let total_bps = fee_config.total_bps();
+  if total_bps > 10_000 {
+    return Err(ContractError::InvalidFeeTotal {
+      total_bps: total_bps as u32,
+    });
+  }
+  // the house's cut and the referrer's share both come out of each payment,
+  // so together they can't exceed the payment itself
+  let referral_bps = fee_config.referral_bps as u32;
+  if referral_bps > 10_000 {
+    return Err(ContractError::InvalidFeeTotal {
+      total_bps: referral_bps,
+    });
+  }
+  if let Some(rule) = fee_config
+    .house_revenue
+    .iter()
+    .find(|x| x.bps as u32 + referral_bps > 10_000)
+  {
+    return Err(ContractError::InvalidFeeTotal {
+      total_bps: rule.bps as u32 + referral_bps,
+    });
+  }
   Ok(())
 }
 
@@ -125,6 +674,12 @@ pub fn repository(store: &dyn Storage) -> ContractResult<Repository> {
   Ok(Repository::new(&REPO_CONTRACT_ADDR.load(store)?))
 }
 
+/// Load the fee config. Raffles created before fees were configurable are
+/// given the fee schedule of their contract version when migrated.
+pub fn fee_config(store: &dyn Storage) -> ContractResult<FeeConfig> {
+  Ok(FEE_CONFIG.load(store)?)
+}
+
 /// Helper function that returns true if given wallet (principal) is authorized
 /// by ACL to the given action.
 pub fn is_allowed(
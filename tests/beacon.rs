@@ -0,0 +1,186 @@
+mod common;
+
+use common::*;
+use cosmwasm_std::{Addr, HexBinary};
+use cw_multi_test::{App, Executor};
+use cw_raffle::{
+  models::{RaffleStatus, RandomnessSource},
+  msg::{DrawProofResponse, ExecuteMsg, NoisCallback, QueryMsg},
+  state::{BEACON_JOB_ID, DRAW_TIMEOUT_SECONDS},
+};
+
+const PRICE: u128 = 1_000_000;
+
+/// Instantiate a beacon raffle with tickets sold to two buyers, returning the
+/// addresses of the raffle and its mock randomness proxy.
+fn setup(app: &mut App) -> (Addr, Addr) {
+  fund_wallets(app, &["buyer1", "buyer2"], 10_000_000);
+  let repo = instantiate_mock(app, "repository");
+  let proxy = instantiate_mock(app, "proxy");
+  let raffle_addr = instantiate_raffle(
+    app,
+    &repo,
+    &build_instantiate_msg(
+      PRICE,
+      None,
+      Some(RandomnessSource::Beacon {
+        proxy_address: proxy.clone(),
+      }),
+    ),
+  );
+  buy_tickets(app, &raffle_addr, "buyer1", 2, None);
+  buy_tickets(app, &raffle_addr, "buyer2", 3, None);
+  (raffle_addr, proxy)
+}
+
+fn choose_winner(
+  app: &mut App,
+  raffle_addr: &Addr,
+) {
+  app
+    .execute_contract(
+      Addr::unchecked(OWNER),
+      raffle_addr.clone(),
+      &ExecuteMsg::ChooseWinner { secret: None },
+      &[],
+    )
+    .unwrap();
+}
+
+/// Deliver randomness to the raffle as `sender`, returning the contract error,
+/// if any.
+fn nois_receive(
+  app: &mut App,
+  raffle_addr: &Addr,
+  sender: &Addr,
+  job_id: &str,
+  randomness: Vec<u8>,
+) -> Option<String> {
+  let published = app.block_info().time;
+  app
+    .execute_contract(
+      sender.clone(),
+      raffle_addr.clone(),
+      &ExecuteMsg::NoisReceive {
+        callback: NoisCallback {
+          job_id: job_id.to_string(),
+          published,
+          randomness: HexBinary::from(randomness),
+        },
+      },
+      &[],
+    )
+    .err()
+    .map(|err| err.root_cause().to_string())
+}
+
+#[test]
+fn proxy_callback_draws_winner() {
+  let mut app = App::default();
+  let (raffle_addr, proxy) = setup(&mut app);
+
+  choose_winner(&mut app, &raffle_addr);
+  let raffle = query_raffle(&app, &raffle_addr);
+  assert_eq!(raffle.status, RaffleStatus::Drawing);
+  assert_eq!(raffle.draw_requested_at, Some(app.block_info().time));
+  assert!(raffle.winners.is_empty());
+
+  // only the configured proxy can deliver randomness
+  assert_eq!(
+    nois_receive(
+      &mut app,
+      &raffle_addr,
+      &Addr::unchecked("buyer1"),
+      BEACON_JOB_ID,
+      vec![7; 32]
+    ),
+    Some("NotAuthorized".to_string())
+  );
+
+  // randomness must be for the raffle's job and exactly 32 bytes
+  assert_eq!(
+    nois_receive(&mut app, &raffle_addr, &proxy, "other_job", vec![7; 32]),
+    Some("InvalidRandomness".to_string())
+  );
+  assert_eq!(
+    nois_receive(&mut app, &raffle_addr, &proxy, BEACON_JOB_ID, vec![7; 31]),
+    Some("InvalidRandomness".to_string())
+  );
+  assert_eq!(
+    query_raffle(&app, &raffle_addr).status,
+    RaffleStatus::Drawing
+  );
+
+  assert_eq!(
+    nois_receive(&mut app, &raffle_addr, &proxy, BEACON_JOB_ID, vec![7; 32]),
+    None
+  );
+  let raffle = query_raffle(&app, &raffle_addr);
+  assert_eq!(raffle.status, RaffleStatus::Complete);
+  assert_eq!(raffle.winners.len(), 1);
+
+  // the draw is recorded with the beacon's randomness
+  let proof: DrawProofResponse = app
+    .wrap()
+    .query_wasm_smart(&raffle_addr, &QueryMsg::DrawProof {})
+    .unwrap();
+  let record = proof.record.unwrap();
+  assert_eq!(record.entropy, Some(HexBinary::from(vec![7; 32])));
+  assert_eq!(record.draws[0].winner, raffle.winners[0].1);
+
+  // randomness can't be delivered twice
+  assert_eq!(
+    nois_receive(&mut app, &raffle_addr, &proxy, BEACON_JOB_ID, vec![8; 32]),
+    Some("NotActive".to_string())
+  );
+}
+
+#[test]
+fn timed_out_draw_can_be_canceled() {
+  let mut app = App::default();
+  let (raffle_addr, proxy) = setup(&mut app);
+
+  choose_winner(&mut app, &raffle_addr);
+
+  let cancel = |app: &mut App| {
+    app
+      .execute_contract(
+        Addr::unchecked(OWNER),
+        raffle_addr.clone(),
+        &ExecuteMsg::Cancel {},
+        &[],
+      )
+      .map(|_| ())
+      .map_err(|err| err.root_cause().to_string())
+  };
+
+  // the proxy still has time to call back
+  app.update_block(|block| block.time = block.time.plus_seconds(DRAW_TIMEOUT_SECONDS - 1));
+  assert_eq!(cancel(&mut app), Err("NotAuthorized".to_string()));
+
+  app.update_block(|block| block.time = block.time.plus_seconds(1));
+  assert_eq!(cancel(&mut app), Ok(()));
+  assert_eq!(
+    query_raffle(&app, &raffle_addr).status,
+    RaffleStatus::Canceled
+  );
+
+  // a late callback no longer draws winners
+  assert_eq!(
+    nois_receive(&mut app, &raffle_addr, &proxy, BEACON_JOB_ID, vec![7; 32]),
+    Some("NotActive".to_string())
+  );
+
+  // buyers are refunded what they paid
+  let buyer = Addr::unchecked("buyer2");
+  let balance_before = query_balance(&app, &buyer);
+  app
+    .execute_contract(
+      buyer.clone(),
+      raffle_addr.clone(),
+      &ExecuteMsg::ClaimRefund {},
+      &[],
+    )
+    .unwrap();
+  assert_eq!(query_balance(&app, &buyer) - balance_before, 3 * PRICE);
+}
@@ -0,0 +1,218 @@
+//! Helpers shared by the multi-test integration tests.
+#![allow(dead_code)]
+
+use cosmwasm_std::{
+  coins, from_slice, to_binary, Addr, Binary, Coin, Deps, DepsMut, Empty, Env, MessageInfo,
+  Response, StdResult, Uint128,
+};
+use cw_lib::models::{Token, TokenAmount};
+use cw_multi_test::{App, AppResponse, Contract, ContractWrapper, Executor};
+use cw_raffle::{
+  contract,
+  models::{FeeConfig, Raffle, RaffleAsset, RandomnessSource, RoyaltyRecipient},
+  msg::{ExecuteMsg, InstantiateMsg, QueryMsg, QuotePriceResponse, SelectResponse},
+};
+
+pub const DENOM: &str = "ujuno";
+pub const OWNER: &str = "owner";
+pub const ROYALTY_RECIPIENT: &str = "royalties";
+
+/// Accepts any instantiate or execute msg, keeping whatever funds are sent.
+/// Stands in for the repository, house and other contracts the raffle sends
+/// msgs to.
+fn mock_instantiate(
+  _deps: DepsMut,
+  _env: Env,
+  _info: MessageInfo,
+  _msg: Empty,
+) -> StdResult<Response> {
+  Ok(Response::default())
+}
+
+fn mock_execute(
+  _deps: DepsMut,
+  _env: Env,
+  _info: MessageInfo,
+  _msg: Empty,
+) -> StdResult<Response> {
+  Ok(Response::default())
+}
+
+fn mock_query(
+  _deps: Deps,
+  _env: Env,
+  _msg: Empty,
+) -> StdResult<Binary> {
+  to_binary(&Empty {})
+}
+
+pub fn mock_contract() -> Box<dyn Contract<Empty>> {
+  Box::new(ContractWrapper::new(
+    mock_execute,
+    mock_instantiate,
+    mock_query,
+  ))
+}
+
+pub fn raffle_contract() -> Box<dyn Contract<Empty>> {
+  Box::new(ContractWrapper::new(
+    contract::execute,
+    contract::instantiate,
+    contract::query,
+  ))
+}
+
+/// Instantiate a mock contract, returning its address.
+pub fn instantiate_mock(
+  app: &mut App,
+  label: &str,
+) -> Addr {
+  let code_id = app.store_code(mock_contract());
+  app
+    .instantiate_contract(code_id, Addr::unchecked(OWNER), &Empty {}, &[], label, None)
+    .unwrap()
+}
+
+/// Give each wallet the given amount of the ticket price denom.
+pub fn fund_wallets(
+  app: &mut App,
+  wallets: &[&str],
+  amount: u128,
+) {
+  app.init_modules(|router, _, storage| {
+    for wallet in wallets.iter() {
+      router
+        .bank
+        .init_balance(storage, &Addr::unchecked(*wallet), coins(amount, DENOM))
+        .unwrap();
+    }
+  });
+}
+
+/// Instantiate msg for a raffle priced in the native denom, whose only prize
+/// is delivered off-chain, so that no funding or escrow is needed.
+pub fn build_instantiate_msg(
+  price: u128,
+  fee_config: Option<FeeConfig>,
+  randomness_source: Option<RandomnessSource>,
+) -> InstantiateMsg {
+  InstantiateMsg {
+    owner: Addr::unchecked(OWNER),
+    acl_address: None,
+    ticket_supply: None,
+    ticket_sales_start_at: None,
+    ticket_sales_end_at: None,
+    ticket_sales_target: None,
+    max_tickets_per_wallet: None,
+    max_tickets_per_order: None,
+    sale_gate: None,
+    allowlist: None,
+    merkle_root: None,
+    price_tiers: None,
+    royalties: vec![RoyaltyRecipient {
+      name: None,
+      address: Addr::unchecked(ROYALTY_RECIPIENT),
+      pct: 100,
+    }],
+    raffle_name: "Test Raffle".to_string(),
+    org_name: None,
+    org_wallet: None,
+    org_logo_url: None,
+    youtube_video_id: None,
+    website: None,
+    description: None,
+    socials: None,
+    terms: None,
+    assets: vec![RaffleAsset::Asset {
+      name: "Prize".to_string(),
+      description: None,
+      url: None,
+      image: None,
+      address: None,
+      terms: None,
+      rank: None,
+    }],
+    price: TokenAmount {
+      token: Token::Native {
+        denom: DENOM.to_string(),
+      },
+      amount: Uint128::from(price),
+    },
+    style: from_slice(br##"{"ui_base_color":"#000000","bg_color":"#ffffff"}"##).unwrap(),
+    selection_mode: None,
+    randomness_source,
+    fee_config,
+  }
+}
+
+/// Instantiate a raffle as the mock repository contract, which the raffle
+/// sends its index updates to.
+pub fn instantiate_raffle(
+  app: &mut App,
+  repo: &Addr,
+  msg: &InstantiateMsg,
+) -> Addr {
+  let code_id = app.store_code(raffle_contract());
+  app
+    .instantiate_contract(code_id, repo.clone(), msg, &[], "raffle", None)
+    .unwrap()
+}
+
+pub fn query_raffle(
+  app: &App,
+  raffle: &Addr,
+) -> Raffle {
+  let resp: SelectResponse = app
+    .wrap()
+    .query_wasm_smart(
+      raffle,
+      &QueryMsg::Select {
+        fields: Some(vec!["raffle".to_string()]),
+        wallet: None,
+      },
+    )
+    .unwrap();
+  resp.raffle.unwrap()
+}
+
+pub fn query_balance(
+  app: &App,
+  addr: &Addr,
+) -> u128 {
+  app.wrap().query_balance(addr, DENOM).unwrap().amount.u128()
+}
+
+/// Buy tickets, paying exactly the price quoted by the raffle.
+pub fn buy_tickets(
+  app: &mut App,
+  raffle: &Addr,
+  buyer: &str,
+  count: u32,
+  referrer: Option<&str>,
+) -> AppResponse {
+  let quote: QuotePriceResponse = app
+    .wrap()
+    .query_wasm_smart(
+      raffle,
+      &QueryMsg::QuotePrice {
+        count,
+        wallet: Some(Addr::unchecked(buyer)),
+      },
+    )
+    .unwrap();
+  app
+    .execute_contract(
+      Addr::unchecked(buyer),
+      raffle.clone(),
+      &ExecuteMsg::BuyTickets {
+        count,
+        message: None,
+        is_visible: false,
+        proof: None,
+        allocation: None,
+        referrer: referrer.map(Addr::unchecked),
+      },
+      &[Coin::new(quote.amount.u128(), DENOM)],
+    )
+    .unwrap()
+}
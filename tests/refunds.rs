@@ -0,0 +1,134 @@
+mod common;
+
+use common::*;
+use cosmwasm_std::{Addr, Uint128};
+use cw_lib::models::Token;
+use cw_multi_test::{App, Executor};
+use cw_raffle::{
+  models::{FeeConfig, FeeRecipient, HouseRevenue, RaffleStatus},
+  msg::ExecuteMsg,
+};
+
+const PRICE: u128 = 1_000_003;
+const HOUSE_BPS: u16 = 500;
+const BUYERS: [&str; 3] = ["buyer1", "buyer2", "buyer3"];
+
+/// Contract balance must always cover refunds owed plus unclaimed referral
+/// earnings.
+fn assert_solvent(
+  app: &App,
+  raffle_addr: &Addr,
+) {
+  let raffle = query_raffle(app, raffle_addr);
+  let balance = query_balance(app, raffle_addr);
+  assert!(
+    Uint128::from(balance) >= raffle.refunds_outstanding + raffle.referral_earnings,
+    "balance {} doesn't cover refunds {} and referral earnings {}",
+    balance,
+    raffle.refunds_outstanding,
+    raffle.referral_earnings
+  );
+}
+
+#[test]
+fn canceled_house_cut_raffle_refunds_every_buyer() {
+  let mut app = App::default();
+  fund_wallets(&mut app, &BUYERS, 100_000_000);
+
+  let repo = instantiate_mock(&mut app, "repository");
+  let house = instantiate_mock(&mut app, "house");
+  let fee_config = FeeConfig {
+    recipients: vec![FeeRecipient {
+      name: None,
+      address: Addr::unchecked("gelotto"),
+      bps: 200,
+    }],
+    house_revenue: vec![HouseRevenue {
+      token: Token::Native {
+        denom: DENOM.to_string(),
+      },
+      house_address: house.clone(),
+      bps: HOUSE_BPS,
+    }],
+    remainder_address: None,
+    referral_bps: 300,
+  };
+  let raffle_addr = instantiate_raffle(
+    &mut app,
+    &repo,
+    &build_instantiate_msg(PRICE, Some(fee_config), None),
+  );
+
+  // (buyer, ticket count, referrer) in purchase order
+  let orders: [(&str, u32, Option<&str>); 5] = [
+    ("buyer1", 1, None),
+    ("buyer2", 7, Some("referrer")),
+    ("buyer1", 3, None),
+    ("buyer3", 2, Some("referrer")),
+    ("buyer2", 1, Some("buyer3")),
+  ];
+
+  let mut expected_refunds = [0u128; 3];
+  let mut expected_house_revenue = 0u128;
+  for (buyer, count, referrer) in orders.iter() {
+    buy_tickets(&mut app, &raffle_addr, buyer, *count, *referrer);
+    let amount_paid = PRICE * (*count as u128);
+    let house_revenue = amount_paid * (HOUSE_BPS as u128) / 10_000;
+    let i = BUYERS.iter().position(|b| b == buyer).unwrap();
+    expected_refunds[i] += amount_paid - house_revenue;
+    expected_house_revenue += house_revenue;
+    assert_solvent(&app, &raffle_addr);
+  }
+  assert_eq!(query_balance(&app, &house), expected_house_revenue);
+
+  app
+    .execute_contract(
+      Addr::unchecked(OWNER),
+      raffle_addr.clone(),
+      &ExecuteMsg::Cancel {},
+      &[],
+    )
+    .unwrap();
+
+  let raffle = query_raffle(&app, &raffle_addr);
+  assert_eq!(raffle.status, RaffleStatus::Canceled);
+  assert_eq!(raffle.referral_earnings, Uint128::zero());
+  assert_eq!(
+    raffle.refunds_outstanding,
+    Uint128::from(expected_refunds.iter().sum::<u128>())
+  );
+  assert_solvent(&app, &raffle_addr);
+
+  for (i, buyer) in BUYERS.iter().enumerate() {
+    let buyer_addr = Addr::unchecked(*buyer);
+    let balance_before = query_balance(&app, &buyer_addr);
+    app
+      .execute_contract(
+        buyer_addr.clone(),
+        raffle_addr.clone(),
+        &ExecuteMsg::ClaimRefund {},
+        &[],
+      )
+      .unwrap();
+    assert_eq!(
+      query_balance(&app, &buyer_addr) - balance_before,
+      expected_refunds[i]
+    );
+    assert_solvent(&app, &raffle_addr);
+
+    // refunds can only be claimed once
+    app
+      .execute_contract(
+        buyer_addr,
+        raffle_addr.clone(),
+        &ExecuteMsg::ClaimRefund {},
+        &[],
+      )
+      .unwrap_err();
+  }
+
+  // every refund was paid out of what the contract held
+  let raffle = query_raffle(&app, &raffle_addr);
+  assert_eq!(raffle.refunds_outstanding, Uint128::zero());
+  assert_eq!(query_balance(&app, &raffle_addr), 0);
+}